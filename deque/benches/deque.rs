@@ -0,0 +1,78 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::collections::{LinkedList, VecDeque};
+use std::hint::black_box;
+use rust_dust_deque::deque::Deque;
+use rust_dust_deque::raw::RawDeque;
+
+// Each iteration uses the deque as a queue: pushes `depth` elements at the back and pops them
+// all off the front. The collections are created outside the timed loop.
+fn push_pop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push_pop");
+    for depth in [10, 1000] {
+        group.bench_with_input(BenchmarkId::new("Deque", depth), &depth, |b, &depth| {
+            let mut deque = Deque::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    deque.push_back(black_box(i));
+                }
+                while let Some(i) = deque.pop() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("RawDeque", depth), &depth, |b, &depth| {
+            let mut deque = RawDeque::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    deque.push_back(black_box(i));
+                }
+                while let Some(i) = deque.pop() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("LinkedList", depth), &depth, |b, &depth| {
+            let mut list = LinkedList::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    list.push_back(black_box(i));
+                }
+                while let Some(i) = list.pop_front() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("VecDeque", depth), &depth, |b, &depth| {
+            let mut deque = VecDeque::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    deque.push_back(black_box(i));
+                }
+                while let Some(i) = deque.pop_front() {
+                    black_box(i);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+// Sums 1000 elements with the borrowing iterator.
+fn iter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+    let mut deque = Deque::new();
+    let mut raw = RawDeque::new();
+    let mut list = LinkedList::new();
+    for i in 0..1000u64 {
+        deque.push_back(i);
+        raw.push_back(i);
+        list.push_back(i);
+    }
+    group.bench_function("Deque", |b| b.iter(|| deque.iter().map(|elem| *elem).sum::<u64>()));
+    group.bench_function("RawDeque", |b| b.iter(|| raw.iter().sum::<u64>()));
+    group.bench_function("LinkedList", |b| b.iter(|| list.iter().sum::<u64>()));
+    group.finish();
+}
+
+criterion_group!(benches, push_pop, iter);
+criterion_main!(benches);
@@ -0,0 +1,415 @@
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::double_ended::DoubleEnded;
+use crate::raw::RawDeque;
+
+/// Error returned by the pushes of `BlockingDeque`, with the element that was not pushed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushError<E> {
+    /// The deque was full, and stayed full as long as the push was willing to wait.
+    Full(E),
+    /// The deque has been closed.
+    Closed(E),
+}
+
+impl<E> PushError<E> {
+    /// The element that was not pushed.
+    pub fn into_inner(self) -> E {
+        match self {
+            PushError::Full(elem) | PushError::Closed(elem) => elem,
+        }
+    }
+}
+
+impl<E> Display for PushError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushError::Full(_) => write!(f, "deque is full"),
+            PushError::Closed(_) => write!(f, "deque is closed"),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug> std::error::Error for PushError<E> {}
+
+/// Error returned by the non-blocking and timed pops of `BlockingDeque`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopError {
+    /// The deque was empty, and stayed empty as long as the pop was willing to wait.
+    Empty,
+    /// The deque has been closed, and all its elements have been popped.
+    Closed,
+}
+
+impl Display for PopError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PopError::Empty => write!(f, "deque is empty"),
+            PopError::Closed => write!(f, "deque is closed"),
+        }
+    }
+}
+
+impl std::error::Error for PopError {}
+
+/// How long a push or pop waits for room or for an element.
+#[derive(Clone, Copy)]
+enum Wait {
+    Never,
+    Until(Instant),
+    Forever,
+}
+
+impl Wait {
+    fn timeout(timeout: Duration) -> Self {
+        // A deadline too far out to represent is as good as none.
+        Instant::now().checked_add(timeout).map_or(Wait::Forever, Wait::Until)
+    }
+}
+
+struct State<D> {
+    deque: D,
+    closed: bool,
+}
+
+/// Deque for handing elements between threads: pushes wait while it is full, and pops wait
+/// while it is empty. Any `DoubleEnded` deque that is `Send` can be used inside, behind a `Mutex`;
+/// the default is `RawDeque`, since `Deque` shares its nodes through `Rc`s and cannot be sent.
+///
+/// `close()` ends the hand-over: pushes fail from then on, and once the elements already pushed
+/// have been popped, so do pops. Every waiting thread is woken up to find out.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use rust_dust_deque::blocking::BlockingDeque;
+///
+/// let deque = Arc::new(BlockingDeque::<u32>::new(2));
+/// let producer = {
+///     let deque = deque.clone();
+///     thread::spawn(move || {
+///         for i in 1..=10 {
+///             deque.push_back(i).unwrap();  // Waits while 2 elements are in the deque.
+///         }
+///         deque.close();
+///     })
+/// };
+/// let mut sum = 0;
+/// while let Some(i) = deque.pop() {  // `None` once closed and drained.
+///     sum += i;
+/// }
+/// producer.join().unwrap();
+/// assert_eq!(sum, 55);
+/// ```
+pub struct BlockingDeque<E, D = RawDeque<E>> {
+    state: Mutex<State<D>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    // `D` holds the elements; this only ties `E` to `D`, without affecting `Send` or `Sync`.
+    _elem: PhantomData<fn() -> E>,
+}
+
+impl<E, D: DoubleEnded<Elem = E> + Default> BlockingDeque<E, D> {
+    /// Create an empty deque that holds at most `capacity` elements. Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        BlockingDeque {
+            state: Mutex::new(State { deque: D::default(), closed: false }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+            _elem: PhantomData,
+        }
+    }
+
+    /// Create an empty deque without a capacity, whose pushes never wait.
+    pub fn unbounded() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    /// Insert at the front, waiting for room if the deque is full.
+    /// Fails only if the deque is closed, before or while waiting.
+    pub fn push(&self, elem: E) -> Result<(), PushError<E>> {
+        self.push_end(elem, false, Wait::Forever)
+    }
+
+    /// Insert at the back, waiting for room if the deque is full.
+    /// Fails only if the deque is closed, before or while waiting.
+    pub fn push_back(&self, elem: E) -> Result<(), PushError<E>> {
+        self.push_end(elem, true, Wait::Forever)
+    }
+
+    /// Insert at the front if there is room, without waiting.
+    pub fn try_push(&self, elem: E) -> Result<(), PushError<E>> {
+        self.push_end(elem, false, Wait::Never)
+    }
+
+    /// Insert at the back if there is room, without waiting.
+    pub fn try_push_back(&self, elem: E) -> Result<(), PushError<E>> {
+        self.push_end(elem, true, Wait::Never)
+    }
+
+    /// Insert at the front, waiting at most `timeout` for room.
+    pub fn push_timeout(&self, elem: E, timeout: Duration) -> Result<(), PushError<E>> {
+        self.push_end(elem, false, Wait::timeout(timeout))
+    }
+
+    /// Insert at the back, waiting at most `timeout` for room.
+    pub fn push_back_timeout(&self, elem: E, timeout: Duration) -> Result<(), PushError<E>> {
+        self.push_end(elem, true, Wait::timeout(timeout))
+    }
+
+    /// Remove from the front, waiting for an element if the deque is empty.
+    /// Returns `None` only once the deque is closed and empty.
+    pub fn pop(&self) -> Option<E> {
+        self.pop_end(false, Wait::Forever).ok()
+    }
+
+    /// Remove from the back, waiting for an element if the deque is empty.
+    /// Returns `None` only once the deque is closed and empty.
+    pub fn pop_back(&self) -> Option<E> {
+        self.pop_end(true, Wait::Forever).ok()
+    }
+
+    /// Remove from the front if there is an element, without waiting.
+    pub fn try_pop(&self) -> Result<E, PopError> {
+        self.pop_end(false, Wait::Never)
+    }
+
+    /// Remove from the back if there is an element, without waiting.
+    pub fn try_pop_back(&self) -> Result<E, PopError> {
+        self.pop_end(true, Wait::Never)
+    }
+
+    /// Remove from the front, waiting at most `timeout` for an element.
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<E, PopError> {
+        self.pop_end(false, Wait::timeout(timeout))
+    }
+
+    /// Remove from the back, waiting at most `timeout` for an element.
+    pub fn pop_back_timeout(&self, timeout: Duration) -> Result<E, PopError> {
+        self.pop_end(true, Wait::timeout(timeout))
+    }
+
+    /// Refuse any more pushes, and wake up all waiting threads. Pops still return the elements
+    /// that are left. Closing twice does nothing.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    /// `true` once `close()` has been called.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// Number of elements in the deque. Other threads may change it right after.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().deque.len()
+    }
+
+    /// `true` if the deque has no elements. Other threads may change that right after.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of elements this deque will hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn push_end(&self, elem: E, back: bool, wait: Wait) -> Result<(), PushError<E>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                return Err(PushError::Closed(elem));
+            }
+            if state.deque.len() < self.capacity {
+                break;
+            }
+            // Loop after waking up: another thread may have taken the room, or nobody made any.
+            state = match wait {
+                Wait::Never => return Err(PushError::Full(elem)),
+                Wait::Forever => self.not_full.wait(state).unwrap(),
+                Wait::Until(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    None => return Err(PushError::Full(elem)),
+                    Some(left) => self.not_full.wait_timeout(state, left).unwrap().0,
+                },
+            };
+        }
+        if back {
+            state.deque.push_back(elem);
+        } else {
+            state.deque.push(elem);
+        }
+        drop(state);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    fn pop_end(&self, back: bool, wait: Wait) -> Result<E, PopError> {
+        let mut state = self.state.lock().unwrap();
+        let elem = loop {
+            let elem = if back { state.deque.pop_back() } else { state.deque.pop() };
+            if let Some(elem) = elem {
+                break elem;
+            }
+            if state.closed {
+                return Err(PopError::Closed);
+            }
+            state = match wait {
+                Wait::Never => return Err(PopError::Empty),
+                Wait::Forever => self.not_empty.wait(state).unwrap(),
+                Wait::Until(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    None => return Err(PopError::Empty),
+                    Some(left) => self.not_empty.wait_timeout(state, left).unwrap().0,
+                },
+            };
+        };
+        drop(state);
+        self.not_full.notify_one();
+        Ok(elem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::blocking::{BlockingDeque, PopError, PushError};
+    use crate::double_ended::DoubleEnded;
+    use crate::raw::RawDeque;
+    use crate::ring::RingDeque;
+
+    #[test]
+    fn try_test() {
+        let deque = BlockingDeque::<i32>::new(2);
+        assert_eq!(deque.try_pop(), Err(PopError::Empty));
+        deque.try_push_back(2).unwrap();
+        deque.try_push(1).unwrap();
+        assert_eq!(deque.try_push_back(3), Err(PushError::Full(3)));
+        assert_eq!(deque.len(), 2);
+        assert_eq!(deque.try_pop_back(), Ok(2));
+        deque.close();
+        assert!(deque.is_closed());
+        assert_eq!(deque.try_push(3), Err(PushError::Closed(3)));
+        assert_eq!(deque.try_push(3).unwrap_err().into_inner(), 3);
+        // Drains before reporting the close.
+        assert_eq!(deque.try_pop(), Ok(1));
+        assert_eq!(deque.try_pop(), Err(PopError::Closed));
+        assert_eq!(deque.pop(), None);
+    }
+
+    #[test]
+    fn timeout_test() {
+        let deque = BlockingDeque::<i32>::new(1);
+        let start = Instant::now();
+        assert_eq!(deque.pop_timeout(Duration::from_millis(20)), Err(PopError::Empty));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        deque.push(1).unwrap();
+        assert_eq!(deque.push_back_timeout(2, Duration::from_millis(20)), Err(PushError::Full(2)));
+        assert_eq!(deque.pop_back_timeout(Duration::MAX), Ok(1));
+        deque.push_timeout(3, Duration::ZERO).unwrap();
+        assert_eq!(deque.pop_timeout(Duration::ZERO), Ok(3));
+    }
+
+    #[test]
+    fn block_test() {
+        let deque = Arc::new(BlockingDeque::<i32>::new(1));
+        deque.push(1).unwrap();
+        let pusher = {
+            let deque = deque.clone();
+            thread::spawn(move || deque.push(2))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(deque.len(), 1);
+        assert_eq!(deque.pop(), Some(1));
+        pusher.join().unwrap().unwrap();
+        assert_eq!(deque.pop(), Some(2));
+        // A pop waits for a push, even a late one.
+        let popper = {
+            let deque = deque.clone();
+            thread::spawn(move || deque.pop_timeout(Duration::from_secs(60)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        deque.push_back(3).unwrap();
+        assert_eq!(popper.join().unwrap(), Ok(3));
+    }
+
+    #[test]
+    fn close_test() {
+        let deque = Arc::new(BlockingDeque::<i32>::new(1));
+        let poppers = (0..4).map(|_| {
+            let deque = deque.clone();
+            thread::spawn(move || deque.pop())
+        }).collect::<Vec<_>>();
+        thread::sleep(Duration::from_millis(20));
+        deque.close();
+        for popper in poppers {
+            assert_eq!(popper.join().unwrap(), None);
+        }
+        let deque = Arc::new(BlockingDeque::<i32>::new(1));
+        deque.push(0).unwrap();
+        let pushers = (1..4).map(|i| {
+            let deque = deque.clone();
+            thread::spawn(move || deque.push(i))
+        }).collect::<Vec<_>>();
+        thread::sleep(Duration::from_millis(20));
+        deque.close();
+        for pusher in pushers {
+            assert!(matches!(pusher.join().unwrap(), Err(PushError::Closed(_))));
+        }
+        assert_eq!(deque.pop(), Some(0));
+    }
+
+    fn pipeline<D: DoubleEnded<Elem = u64> + Default + Send + 'static>() {
+        let deque = Arc::new(BlockingDeque::<u64, D>::new(16));
+        let producers = (0..4).map(|p| {
+            let deque = deque.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    if i % 2 == 0 {
+                        deque.push_back(p * 1000 + i).unwrap();
+                    } else {
+                        deque.push(p * 1000 + i).unwrap();
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+        let consumers = (0..3).map(|_| {
+            let deque = deque.clone();
+            thread::spawn(move || {
+                let mut sum = 0;
+                while let Some(i) = deque.pop() {
+                    assert!(deque.len() <= 16);
+                    sum += i;
+                }
+                sum
+            })
+        }).collect::<Vec<_>>();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        deque.close();
+        let sum = consumers.into_iter().map(|consumer| consumer.join().unwrap()).sum::<u64>();
+        assert_eq!(sum, (0..4000).sum());
+    }
+
+    #[test]
+    fn pipeline_test() {
+        pipeline::<RawDeque<u64>>();
+        pipeline::<RingDeque<u64>>();
+    }
+
+    #[test]
+    fn send_sync_test() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<BlockingDeque<String>>();
+        assert_send_sync::<BlockingDeque<String, RingDeque<String>>>();
+    }
+}
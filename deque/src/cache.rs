@@ -0,0 +1,582 @@
+use std::cell::Ref;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::deque::{Deque, NodeRef};
+
+/// Which entry `Cache` evicts when it is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Least recently used: the entry that was read or written longest ago.
+    Lru,
+    /// Least frequently used: the entry with the fewest reads and writes, and of those,
+    /// the least recently used one.
+    Lfu,
+}
+
+/// Why an entry left the cache, as told to the eviction callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eviction {
+    /// Evicted to make room for another entry, or too heavy to be stored at all.
+    Capacity,
+    /// Its time to live ran out.
+    Expired,
+}
+
+/// Hit and miss counts. Reads that find an expired entry count as misses.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+}
+
+impl CacheStats {
+    /// Fraction of reads that were hits, or 0 before the first read.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            reads => self.hits as f64 / reads as f64,
+        }
+    }
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    weight: usize,
+    expires: Option<Instant>,
+    // Reads and writes, for LFU.
+    freq: u64,
+}
+
+/// The entries, in eviction order.
+enum Order<K, V> {
+    // Least recently used at the head.
+    Lru(Deque<Entry<K, V>>),
+    // One LRU bucket per frequency in use, and those frequencies in ascending order,
+    // so that the lowest one is always at the head of `freqs`.
+    Lfu { buckets: HashMap<u64, Bucket<K, V>>, freqs: Deque<u64> },
+}
+
+/// The entries with one frequency, least recently used at the head, and the frequency's node in `freqs`.
+struct Bucket<K, V> {
+    entries: Deque<Entry<K, V>>,
+    freq_node: NodeRef<u64>,
+}
+
+impl<K, V> Order<K, V> {
+    /// Insert a new entry, used once.
+    fn insert(&mut self, entry: Entry<K, V>) -> NodeRef<Entry<K, V>> {
+        match self {
+            Order::Lru(deque) => deque.push_back_node(entry),
+            Order::Lfu { buckets, freqs } => {
+                // No frequency is lower, so the bucket for 1, if any, is first.
+                let bucket = buckets.entry(1).or_insert_with(|| {
+                    Bucket { entries: Deque::new(), freq_node: freqs.push_front_node(1) }
+                });
+                bucket.entries.push_back_node(entry)
+            }
+        }
+    }
+
+    /// Record a read or write of `node`.
+    fn touch(&mut self, node: &NodeRef<Entry<K, V>>) {
+        match self {
+            Order::Lru(deque) => deque.move_node_to_back(node),
+            Order::Lfu { buckets, freqs } => {
+                let freq = node.borrow().elem.freq;
+                if !buckets.contains_key(&(freq + 1)) {
+                    // The next frequency goes right after this one, which is still in use by `node`.
+                    let freq_node = freqs.insert_after_node(&buckets[&freq].freq_node, freq + 1);
+                    buckets.insert(freq + 1, Bucket { entries: Deque::new(), freq_node });
+                }
+                Self::unlink_lfu(buckets, freqs, node);
+                node.borrow_mut().elem.freq = freq + 1;
+                buckets.get_mut(&(freq + 1)).unwrap().entries.link_back_node(node.clone());
+            }
+        }
+    }
+
+    fn remove(&mut self, node: NodeRef<Entry<K, V>>) -> Entry<K, V> {
+        match self {
+            Order::Lru(deque) => deque.remove_node(node),
+            Order::Lfu { buckets, freqs } => {
+                Self::unlink_lfu(buckets, freqs, &node);
+                Rc::try_unwrap(node).ok().expect("node still has other handles").into_inner().elem
+            }
+        }
+    }
+
+    /// Take `node` out of its bucket, dropping the bucket and its frequency if that leaves it empty.
+    fn unlink_lfu(buckets: &mut HashMap<u64, Bucket<K, V>>, freqs: &mut Deque<u64>, node: &NodeRef<Entry<K, V>>) {
+        let freq = node.borrow().elem.freq;
+        let bucket = buckets.get_mut(&freq).unwrap();
+        bucket.entries.unlink_node(node);
+        if bucket.entries.is_empty() {
+            let bucket = buckets.remove(&freq).unwrap();
+            freqs.remove_node(bucket.freq_node);
+        }
+    }
+
+    /// The entry to evict next, other than `keep`. O(1): the entry after `keep`,
+    /// if it would have been the one, is at most one bucket further.
+    fn victim(&self, keep: Option<&NodeRef<Entry<K, V>>>) -> Option<NodeRef<Entry<K, V>>> {
+        let first_other = |entries: &Deque<Entry<K, V>>| {
+            let head = entries.head.clone()?;
+            if keep.is_some_and(|keep| Rc::ptr_eq(keep, &head)) {
+                return head.borrow().next.clone();
+            }
+            Some(head)
+        };
+        match self {
+            Order::Lru(deque) => first_other(deque),
+            Order::Lfu { buckets, freqs } => {
+                let lowest = freqs.head.clone()?;
+                let lowest_freq = lowest.borrow().elem;
+                first_other(&buckets[&lowest_freq].entries).or_else(|| {
+                    let next_freq = lowest.borrow().next.as_ref()?.borrow().elem;
+                    first_other(&buckets[&next_freq].entries)
+                })
+            }
+        }
+    }
+}
+
+type Weigher<K, V> = Box<dyn Fn(&K, &V) -> usize + Send>;
+type EvictionCallback<K, V> = Box<dyn FnMut(K, V, Eviction) + Send>;
+
+/// Cache with LRU or LFU eviction, a capacity by count or by weight, and optional expiry.
+///
+/// The entries are kept in a `Deque`, in eviction order, next to a `HashMap` from each key to its
+/// node, so that `get()` and `put()` find the node in O(1), and move it in O(1) by relinking.
+/// With LFU, there is one deque per frequency instead.
+///
+/// The cache is `Send`, so a cache for many threads can be put behind a `Mutex`. For that, the
+/// weigher, the eviction callback and the clock must be `Send` as well.
+///
+/// ```
+/// use rust_dust_deque::cache::{Cache, EvictionPolicy};
+///
+/// let mut cache = Cache::new(2, EvictionPolicy::Lru);
+/// cache.put("a", 1);
+/// cache.put("b", 2);
+/// assert_eq!(cache.get(&"a").as_deref(), Some(&1));
+/// cache.put("c", 3);  // Evicts "b", the least recently used.
+/// assert!(cache.get(&"b").is_none());
+/// assert_eq!(cache.stats().hits, 1);
+/// assert_eq!(cache.stats().misses, 1);
+/// ```
+pub struct Cache<K, V> {
+    // Handles to the nodes in `order`.
+    map: HashMap<K, NodeRef<Entry<K, V>>>,
+    order: Order<K, V>,
+    weight: usize,
+    max_weight: usize,
+    weigher: Weigher<K, V>,
+    ttl: Option<Duration>,
+    on_evict: Option<EvictionCallback<K, V>>,
+    clock: Box<dyn Fn() -> Instant + Send>,
+    stats: CacheStats,
+}
+
+// SAFETY: the `Rc`s in `map` and `order` only point at nodes of this cache, and none of them, nor
+// any reference into a node, is ever handed out, except for `Ref`s that borrow the cache itself.
+// So all handles to a node move between threads together, along with the cache, and their
+// non-atomic reference counts are never touched by two threads at once. The cache is not `Sync`,
+// as `peek()` updates `RefCell` borrow counts through `&self`.
+unsafe impl<K: Send, V: Send> Send for Cache<K, V> {}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> {
+    /// Create an empty cache for at most `capacity` entries.
+    pub fn new(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self::weighted(capacity, policy, |_, _| 1)
+    }
+
+    /// Create an empty cache for entries weighing at most `max_weight` in total, as weighed by
+    /// `weigher`, e.g. by their size in bytes. An entry heavier than `max_weight` is not stored.
+    pub fn weighted<F: Fn(&K, &V) -> usize + Send + 'static>(max_weight: usize, policy: EvictionPolicy, weigher: F) -> Self {
+        let order = match policy {
+            EvictionPolicy::Lru => Order::Lru(Deque::new()),
+            EvictionPolicy::Lfu => Order::Lfu { buckets: HashMap::new(), freqs: Deque::new() },
+        };
+        Cache {
+            map: HashMap::new(),
+            order,
+            weight: 0,
+            max_weight,
+            weigher: Box::new(weigher),
+            ttl: None,
+            on_evict: None,
+            clock: Box::new(Instant::now),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Expire entries `ttl` after they were put. Expired entries are dropped when they are read or
+    /// replaced, or by `purge_expired()`; until then, they take up room.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Call `on_evict` with every entry that is evicted or expires. Not called for entries
+    /// that are removed, replaced, or cleared, since those are either returned or asked for.
+    pub fn with_eviction_callback<F: FnMut(K, V, Eviction) + Send + 'static>(mut self, on_evict: F) -> Self {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    /// Tell the time with `clock` instead of `Instant::now()`, e.g. to test expiry without waiting.
+    pub fn with_clock<F: Fn() -> Instant + Send + 'static>(mut self, clock: F) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// The value for `key`, if it is cached and has not expired. Counts as a use of the entry.
+    pub fn get<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<Ref<'_, V>> where K: std::borrow::Borrow<Q> {
+        let Some(node) = self.map.get(key).cloned() else {
+            self.stats.misses += 1;
+            return None;
+        };
+        if self.is_expired(&node) {
+            self.stats.misses += 1;
+            let entry = self.take(node);
+            self.evict(entry, Eviction::Expired);
+            return None;
+        }
+        self.stats.hits += 1;
+        self.order.touch(&node);
+        self.map.get(key).map(|node| Ref::map(node.borrow(), |node| &node.elem.value))
+    }
+
+    /// The value for `key`, if it is cached and has not expired, without counting as a use,
+    /// or in the statistics.
+    pub fn peek<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<Ref<'_, V>> where K: std::borrow::Borrow<Q> {
+        let node = self.map.get(key).filter(|node| !self.is_expired(node))?;
+        Some(Ref::map(node.borrow(), |node| &node.elem.value))
+    }
+
+    /// `true` if `key` is cached and has not expired. Does not count as a use.
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool where K: std::borrow::Borrow<Q> {
+        self.peek(key).is_some()
+    }
+
+    /// Cache `value` under `key`, evicting entries as needed to make room. Returns the value it
+    /// replaces, if that had not expired. Replacing counts as a use of the entry, which keeps its
+    /// place among the others while they are evicted.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let weight = (self.weigher)(&key, &value);
+        let mut node = self.map.get(&key).cloned();
+        if let Some(old) = node.take_if(|old| self.is_expired(old)) {
+            let entry = self.take(old);
+            self.evict(entry, Eviction::Expired);
+        }
+        if weight > self.max_weight {
+            let old_value = node.map(|old| self.take(old).value);
+            self.evict(Entry { key, value, weight, expires: None, freq: 1 }, Eviction::Capacity);
+            return old_value;
+        }
+        let old_weight = node.as_ref().map_or(0, |old| old.borrow().elem.weight);
+        while self.weight - old_weight + weight > self.max_weight {
+            let victim = self.order.victim(node.as_ref()).unwrap();
+            let entry = self.take(victim);
+            self.evict(entry, Eviction::Capacity);
+        }
+        let expires = self.ttl.map(|ttl| (self.clock)() + ttl);
+        self.weight = self.weight - old_weight + weight;
+        match node {
+            Some(node) => {
+                self.order.touch(&node);
+                let entry = &mut node.borrow_mut().elem;
+                entry.weight = weight;
+                entry.expires = expires;
+                Some(mem::replace(&mut entry.value, value))
+            }
+            None => {
+                let node = self.order.insert(Entry { key: key.clone(), value, weight, expires, freq: 1 });
+                self.map.insert(key, node);
+                None
+            }
+        }
+    }
+
+    /// Remove the entry for `key`, and return its value if it had not expired.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V> where K: std::borrow::Borrow<Q> {
+        let node = self.map.get(key).cloned()?;
+        let expired = self.is_expired(&node);
+        let entry = self.take(node);
+        if expired {
+            self.evict(entry, Eviction::Expired);
+            return None;
+        }
+        Some(entry.value)
+    }
+
+    /// Drop all expired entries, passing them to the eviction callback, and return how many there
+    /// were. O(n), as it looks at every entry.
+    pub fn purge_expired(&mut self) -> usize {
+        let expired = self.map.iter()
+            .filter(|(_, node)| self.is_expired(node))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in &expired {
+            let node = self.map.get(key).cloned().unwrap();
+            let entry = self.take(node);
+            self.evict(entry, Eviction::Expired);
+        }
+        expired.len()
+    }
+
+    /// Remove all entries, without calling the eviction callback. Keeps the statistics.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order = match self.order {
+            Order::Lru(_) => Order::Lru(Deque::new()),
+            Order::Lfu { .. } => Order::Lfu { buckets: HashMap::new(), freqs: Deque::new() },
+        };
+        self.weight = 0;
+    }
+
+    /// Number of entries, including expired ones that have not been dropped yet.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Total weight of the entries. With `new()`, the same as `len()`.
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    /// The most the entries may weigh in total. With `new()`, the most entries there may be.
+    pub fn max_weight(&self) -> usize {
+        self.max_weight
+    }
+
+    /// Hit, miss, eviction and expiration counts so far.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Start counting from 0 again.
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+
+    fn is_expired(&self, node: &NodeRef<Entry<K, V>>) -> bool {
+        node.borrow().elem.expires.is_some_and(|expires| expires <= (self.clock)())
+    }
+
+    /// Remove the entry in `node` from both the map and the eviction order.
+    fn take(&mut self, node: NodeRef<Entry<K, V>>) -> Entry<K, V> {
+        // Drop the map's handle first, leaving `node` as the last one.
+        self.map.remove(&node.borrow().elem.key);
+        let entry = self.order.remove(node);
+        self.weight -= entry.weight;
+        entry
+    }
+
+    /// Count an entry that has been taken out, and hand it to the eviction callback.
+    fn evict(&mut self, entry: Entry<K, V>, why: Eviction) {
+        match why {
+            Eviction::Capacity => self.stats.evictions += 1,
+            Eviction::Expired => self.stats.expirations += 1,
+        }
+        if let Some(on_evict) = &mut self.on_evict {
+            on_evict(entry.key, entry.value, why);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::cache::{Cache, CacheStats, Eviction, EvictionPolicy};
+
+    #[test]
+    fn lru_test() {
+        let mut cache = Cache::new(3, EvictionPolicy::Lru);
+        for i in 0..3 {
+            assert_eq!(cache.put(i, i * 10), None);
+        }
+        assert_eq!(*cache.get(&0).unwrap(), 0);
+        cache.put(3, 30);  // Evicts 1.
+        assert!(!cache.contains_key(&1));
+        assert_eq!(cache.put(2, 21), Some(20));  // Now 0 is the least recently used.
+        cache.put(4, 40);
+        assert!(!cache.contains_key(&0));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.remove(&3), Some(30));
+        assert_eq!(cache.remove(&3), None);
+        assert_eq!(*cache.peek(&2).unwrap(), 21);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 0, evictions: 2, expirations: 0 });
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.get(&4).is_none());
+        assert_eq!(cache.stats().hit_rate(), 0.5);
+    }
+
+    #[test]
+    fn lfu_test() {
+        let mut cache = Cache::new(3, EvictionPolicy::Lfu);
+        for i in 0..3 {
+            cache.put(i, i);
+        }
+        for _ in 0..3 {
+            cache.get(&0);
+        }
+        cache.get(&1);
+        cache.put(3, 3);  // Evicts 2, used once.
+        assert!(!cache.contains_key(&2));
+        cache.put(4, 4);  // Evicts 3: 4 is not there yet.
+        assert!(!cache.contains_key(&3));
+        cache.get(&4);
+        // 1 and 4 are now both used twice; 1 less recently.
+        cache.put(5, 5);
+        assert!(!cache.contains_key(&1));
+        assert!(cache.contains_key(&0));
+        assert!(cache.contains_key(&4));
+        // Removing the only entry used once drops its frequency, so the next lowest one is used.
+        let mut cache = Cache::weighted(3, EvictionPolicy::Lfu, |_, weight: &usize| *weight);
+        cache.put('a', 1);
+        cache.get(&'a');
+        cache.get(&'a');
+        cache.put('c', 1);
+        cache.get(&'c');
+        cache.put('b', 1);
+        cache.remove(&'b');
+        cache.put('d', 2);  // Evicts 'c', used twice.
+        assert!(!cache.contains_key(&'c'));
+        assert!(cache.contains_key(&'a'));
+        assert_eq!(cache.weight(), 3);
+    }
+
+    #[test]
+    fn lfu_replace_test() {
+        // The lowest frequency stays exact when its last entry is removed or replaced.
+        let mut cache = Cache::new(3, EvictionPolicy::Lfu);
+        cache.put('a', 1);
+        cache.put('b', 1);
+        cache.put('c', 1);
+        cache.get(&'b');
+        cache.get(&'c');
+        cache.get(&'c');
+        cache.remove(&'a');
+        cache.put('d', 1);
+        cache.put('b', 2);  // Replacing counts as a use: 'b' and 'c' are now both used three times.
+        cache.get(&'d');
+        cache.put('e', 1);  // Evicts 'd', used twice.
+        assert!(!cache.contains_key(&'d'));
+        cache.put('f', 1);  // Evicts 'e', used once.
+        assert!(!cache.contains_key(&'e'));
+        assert_eq!(*cache.peek(&'b').unwrap(), 2);
+        // A heavier replacement evicts others, even if they were used more.
+        let mut cache = Cache::weighted(10, EvictionPolicy::Lfu, |_, weight: &usize| *weight);
+        cache.put('a', 5);
+        cache.put('b', 5);
+        for _ in 0..5 {
+            cache.get(&'b');
+        }
+        assert_eq!(cache.put('a', 6), Some(5));
+        assert!(!cache.contains_key(&'b'));
+        assert_eq!(cache.weight(), 6);
+    }
+
+    #[test]
+    fn send_test() {
+        let cache = Arc::new(Mutex::new(Cache::new(100, EvictionPolicy::Lfu)));
+        let handles = (0..4).map(|t| {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    let mut cache = cache.lock().unwrap();
+                    cache.put(i % 150, t);
+                    cache.get(&(i % 10));
+                }
+            })
+        }).collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let cache = cache.lock().unwrap();
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.stats().hits + cache.stats().misses, 4000);
+    }
+
+    #[test]
+    fn weight_test() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let log = evicted.clone();
+        let mut cache = Cache::weighted(10, EvictionPolicy::Lru, |_, value: &String| value.len())
+            .with_eviction_callback(move |key, _, why| log.lock().unwrap().push((key, why)));
+        cache.put(1, "aaaa".to_string());
+        cache.put(2, "bbbb".to_string());
+        assert_eq!(cache.weight(), 8);
+        cache.put(3, "cccccccc".to_string());  // Evicts both.
+        assert_eq!(cache.weight(), 8);
+        assert_eq!(cache.len(), 1);
+        cache.put(4, "x".repeat(11));  // Too heavy.
+        assert_eq!(cache.len(), 1);
+        cache.put(3, "c".to_string());  // Lighter.
+        assert_eq!(cache.weight(), 1);
+        assert_eq!(*evicted.lock().unwrap(), vec![(1, Eviction::Capacity), (2, Eviction::Capacity), (4, Eviction::Capacity)]);
+        assert_eq!(cache.max_weight(), 10);
+    }
+
+    #[test]
+    fn ttl_test() {
+        let start = Instant::now();
+        let now = Arc::new(Mutex::new(start));
+        let clock = now.clone();
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let log = evicted.clone();
+        let mut cache = Cache::new(10, EvictionPolicy::Lru)
+            .with_ttl(Duration::from_secs(60))
+            .with_clock(move || *clock.lock().unwrap())
+            .with_eviction_callback(move |key, _, why| log.lock().unwrap().push((key, why)));
+        cache.put("a".to_string(), 1);
+        *now.lock().unwrap() = start + Duration::from_secs(30);
+        cache.put("b".to_string(), 2);
+        assert_eq!(*cache.get("a").unwrap(), 1);
+        *now.lock().unwrap() = start + Duration::from_secs(60);
+        assert!(cache.get("a").is_none());
+        assert!(cache.contains_key("b"));
+        cache.put("c".to_string(), 3);
+        *now.lock().unwrap() = start + Duration::from_secs(100);
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.len(), 1);
+        // Replacing an expired entry does not return it.
+        *now.lock().unwrap() = start + Duration::from_secs(200);
+        assert_eq!(cache.put("c".to_string(), 4), None);
+        assert_eq!(*evicted.lock().unwrap(), vec![
+            ("a".to_string(), Eviction::Expired),
+            ("b".to_string(), Eviction::Expired),
+            ("c".to_string(), Eviction::Expired),
+        ]);
+        assert_eq!(cache.stats().expirations, 3);
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[test]
+    fn big_test() {
+        for policy in [EvictionPolicy::Lru, EvictionPolicy::Lfu] {
+            let mut cache = Cache::new(1000, policy);
+            for i in 0..100000 {
+                cache.put(i, i);
+                cache.get(&(i / 2));
+            }
+            assert_eq!(cache.len(), 1000);
+            assert_eq!(cache.stats().evictions, 99000);
+        }
+    }
+}
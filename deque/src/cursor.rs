@@ -0,0 +1,490 @@
+use alloc::rc::Rc;
+use core::cell::{Ref, RefCell, RefMut};
+use core::mem;
+use crate::deque::{Deque, DequeNode};
+
+type Link<E> = Option<Rc<RefCell<DequeNode<E>>>>;
+
+impl<E> Deque<E> {
+    /// Read-only cursor on the head element, or on the "ghost" position if the deque is empty.
+    pub fn cursor_front(&self) -> Cursor<'_, E> {
+        Cursor { current: self.head.clone(), index: self.head.as_ref().map(|_| 0), deque: self }
+    }
+
+    /// Read-only cursor on the tail element, or on the "ghost" position if the deque is empty.
+    pub fn cursor_back(&self) -> Cursor<'_, E> {
+        Cursor { current: self.tail.clone(), index: self.size.checked_sub(1), deque: self }
+    }
+
+    /// Editing cursor on the head element, or on the "ghost" position if the deque is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, E> {
+        CursorMut { current: self.head.clone(), index: self.head.as_ref().map(|_| 0), deque: self }
+    }
+
+    /// Editing cursor on the tail element, or on the "ghost" position if the deque is empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, E> {
+        CursorMut { current: self.tail.clone(), index: self.size.checked_sub(1), deque: self }
+    }
+
+    /// Read-only cursor on the element at `index`, which it walks to from the closer end,
+    /// or `None` if there is no such element.
+    pub fn cursor_at(&self, index: usize) -> Option<Cursor<'_, E>> {
+        if index >= self.size {
+            return None;
+        }
+        let mut cursor;
+        if index < self.size / 2 {
+            cursor = self.cursor_front();
+            (0..index).for_each(|_| cursor.move_next());
+        } else {
+            cursor = self.cursor_back();
+            (index..self.size - 1).for_each(|_| cursor.move_prev());
+        }
+        Some(cursor)
+    }
+
+    /// Editing cursor on the element at `index`, which it walks to from the closer end,
+    /// or `None` if there is no such element.
+    pub fn cursor_at_mut(&mut self, index: usize) -> Option<CursorMut<'_, E>> {
+        if index >= self.size {
+            return None;
+        }
+        let size = self.size;
+        let mut cursor;
+        if index < size / 2 {
+            cursor = self.cursor_front_mut();
+            (0..index).for_each(|_| cursor.move_next());
+        } else {
+            cursor = self.cursor_back_mut();
+            (index..size - 1).for_each(|_| cursor.move_prev());
+        }
+        Some(cursor)
+    }
+}
+
+/// Read-only cursor over a `Deque`. A cursor is positioned either on an element or on the "ghost"
+/// position between the tail and the head, so moving past either end wraps around through it.
+/// Holding a clone of the current node's `Rc`, it moves in O(1) in either direction.
+///
+/// ```
+/// use rust_dust_deque::deque::Deque;
+///
+/// let mut deque = Deque::new();
+/// for i in 0..3 {
+///     deque.push_back(i);
+/// }
+/// let mut cursor = deque.cursor_front();
+/// cursor.move_next();
+/// assert_eq!(*cursor.current().unwrap(), 1);
+/// cursor.move_next();
+/// cursor.move_next();
+/// assert!(cursor.current().is_none());  // Ghost.
+/// cursor.move_next();
+/// assert_eq!(cursor.index(), Some(0));
+/// ```
+pub struct Cursor<'a, E> {
+    deque: &'a Deque<E>,
+    current: Link<E>,
+    index: Option<usize>,
+}
+
+impl<E> Cursor<'_, E> {
+    /// Move to the next element, to the ghost position from the tail, or to the head from the ghost position.
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            None => {
+                self.current = self.deque.head.clone();
+                self.index = self.current.as_ref().map(|_| 0);
+            }
+            Some(node) => {
+                self.current = node.borrow().next.clone();
+                self.index = self.current.as_ref().and_then(|_| self.index.map(|i| i + 1));
+            }
+        }
+    }
+
+    /// Move to the previous element, to the ghost position from the head, or to the tail from the ghost position.
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            None => {
+                self.current = self.deque.tail.clone();
+                self.index = self.deque.size.checked_sub(1);
+            }
+            Some(node) => {
+                self.current = node.borrow().prev.clone();
+                self.index = self.current.as_ref().and_then(|_| self.index.map(|i| i - 1));
+            }
+        }
+    }
+
+    /// The current element, or `None` at the ghost position.
+    pub fn current(&self) -> Option<Ref<'_, E>> {
+        self.current.as_ref().map(|node| Ref::map(node.borrow(), |node| &node.elem))
+    }
+
+    /// Position of the current element from the head, or `None` at the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+}
+
+// A cursor holds a clone of the current node's `Rc`, so the deque's `pop()` would fail to unwrap
+// that node while the cursor is alive. Without a `Drop`, the compiler would consider the deque's
+// borrow over after the cursor's last use, even though the `Rc` is only released when it goes
+// out of scope. This empty `drop()` keeps the deque borrowed until then.
+impl<E> Drop for Cursor<'_, E> {
+    fn drop(&mut self) {}
+}
+
+/// Editing cursor over a `Deque`. In addition to what a `Cursor` does, it can insert and remove
+/// elements next to the current position and cut and join deques there, all in O(1), by relinking
+/// nodes, rather than moving elements.
+///
+/// ```
+/// use rust_dust_deque::deque::Deque;
+///
+/// let mut deque = Deque::new();
+/// deque.push_back(1);
+/// deque.push_back(3);
+/// let mut cursor = deque.cursor_front_mut();
+/// cursor.insert_after(2);
+/// cursor.move_next();
+/// *cursor.current().unwrap() *= 10;
+/// assert_eq!(cursor.remove_current(), Some(20));
+/// assert_eq!(*cursor.current().unwrap(), 3);
+/// let front = cursor.split_before();
+/// drop(cursor);  // Releases `deque`.
+/// assert_eq!(front.collect::<Vec<_>>(), vec![1]);
+/// assert_eq!(deque.collect::<Vec<_>>(), vec![3]);
+/// ```
+pub struct CursorMut<'a, E> {
+    deque: &'a mut Deque<E>,
+    current: Link<E>,
+    index: Option<usize>,
+}
+
+impl<E> CursorMut<'_, E> {
+    /// Move to the next element, to the ghost position from the tail, or to the head from the ghost position.
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            None => {
+                self.current = self.deque.head.clone();
+                self.index = self.current.as_ref().map(|_| 0);
+            }
+            Some(node) => {
+                self.current = node.borrow().next.clone();
+                self.index = self.current.as_ref().and_then(|_| self.index.map(|i| i + 1));
+            }
+        }
+    }
+
+    /// Move to the previous element, to the ghost position from the head, or to the tail from the ghost position.
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            None => {
+                self.current = self.deque.tail.clone();
+                self.index = self.deque.size.checked_sub(1);
+            }
+            Some(node) => {
+                self.current = node.borrow().prev.clone();
+                self.index = self.current.as_ref().and_then(|_| self.index.map(|i| i - 1));
+            }
+        }
+    }
+
+    /// The current element, or `None` at the ghost position.
+    pub fn current(&mut self) -> Option<RefMut<'_, E>> {
+        self.current.as_ref().map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.elem))
+    }
+
+    /// Position of the current element from the head, or `None` at the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Insert an element before the current one, or at the tail at the ghost position.
+    pub fn insert_before(&mut self, elem: E) {
+        match &self.current {
+            None => self.deque.push_back(elem),
+            Some(node) => {
+                let prev = node.borrow_mut().prev.take();
+                let new_node = Rc::new(RefCell::new(DequeNode { next: Some(node.clone()), prev: prev.clone(), elem }));
+                match prev {
+                    None => self.deque.head = Some(new_node.clone()),
+                    Some(prev) => prev.borrow_mut().next = Some(new_node.clone()),
+                }
+                node.borrow_mut().prev = Some(new_node);
+                self.deque.size += 1;
+                self.index = self.index.map(|i| i + 1);
+            }
+        }
+    }
+
+    /// Insert an element after the current one, or at the head at the ghost position.
+    pub fn insert_after(&mut self, elem: E) {
+        match &self.current {
+            None => self.deque.push(elem),
+            Some(node) => {
+                let next = node.borrow_mut().next.take();
+                let new_node = Rc::new(RefCell::new(DequeNode { next: next.clone(), prev: Some(node.clone()), elem }));
+                match next {
+                    None => self.deque.tail = Some(new_node.clone()),
+                    Some(next) => next.borrow_mut().prev = Some(new_node.clone()),
+                }
+                node.borrow_mut().next = Some(new_node);
+                self.deque.size += 1;
+            }
+        }
+    }
+
+    /// Remove the current element and return it, moving the cursor to the next one.
+    /// Does nothing and returns `None` at the ghost position.
+    pub fn remove_current(&mut self) -> Option<E> {
+        let node = self.current.take()?;
+        let prev = node.borrow_mut().prev.take();
+        let next = node.borrow_mut().next.take();
+        match &prev {
+            None => self.deque.head = next.clone(),
+            Some(prev) => prev.borrow_mut().next = next.clone(),
+        }
+        match &next {
+            None => self.deque.tail = prev,
+            Some(next) => next.borrow_mut().prev = prev,
+        }
+        self.deque.size -= 1;
+        if next.is_none() {
+            self.index = None;
+        }
+        self.current = next;
+        // The node is now unlinked, so ours is the only reference left.
+        Some(Rc::try_unwrap(node).ok().unwrap().into_inner().elem)
+    }
+
+    /// Split the deque in two before the current element: return the elements from the head up to,
+    /// but not including, the current one as a new deque, and keep the rest.
+    /// At the ghost position, return the entire deque, leaving this one empty.
+    pub fn split_before(&mut self) -> Deque<E> {
+        let Some(node) = &self.current else {
+            return mem::take(self.deque);
+        };
+        let Some(prev) = node.borrow_mut().prev.take() else {
+            return Deque::new();
+        };
+        prev.borrow_mut().next = None;
+        let front_size = self.index.unwrap();
+        let front = Deque { head: self.deque.head.replace(node.clone()), tail: Some(prev), size: front_size };
+        self.deque.size -= front_size;
+        self.index = Some(0);
+        front
+    }
+
+    /// Move all elements of `other` into this deque right after the current element,
+    /// or at the head at the ghost position.
+    pub fn splice_after(&mut self, mut other: Deque<E>) {
+        let (Some(other_head), Some(other_tail)) = (other.head.take(), other.tail.take()) else {
+            return;
+        };
+        let next = match &self.current {
+            None => self.deque.head.replace(other_head),
+            Some(node) => {
+                other_head.borrow_mut().prev = Some(node.clone());
+                node.borrow_mut().next.replace(other_head)
+            }
+        };
+        match next {
+            None => self.deque.tail = Some(other_tail),
+            Some(next) => {
+                next.borrow_mut().prev = Some(other_tail.clone());
+                other_tail.borrow_mut().next = Some(next);
+            }
+        }
+        self.deque.size += mem::take(&mut other.size);
+    }
+}
+
+// See `Cursor`'s `Drop`.
+impl<E> Drop for CursorMut<'_, E> {
+    fn drop(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deque::Deque;
+
+    fn deque_of(elems: &[i32]) -> Deque<i32> {
+        let mut deque = Deque::new();
+        for elem in elems {
+            deque.push_back(*elem);
+        }
+        deque
+    }
+
+    /// Walk the deque with a cursor in both directions, checking links, indices and size.
+    fn contents(deque: &Deque<i32>) -> Vec<i32> {
+        let mut forward = Vec::new();
+        let mut cursor = deque.cursor_front();
+        while let Some(elem) = cursor.current().map(|elem| *elem) {
+            assert_eq!(cursor.index(), Some(forward.len()));
+            forward.push(elem);
+            cursor.move_next();
+        }
+        let mut backward = Vec::new();
+        let mut cursor = deque.cursor_back();
+        while let Some(elem) = cursor.current().map(|elem| *elem) {
+            backward.push(elem);
+            cursor.move_prev();
+        }
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward.len(), deque.len());
+        forward
+    }
+
+    #[test]
+    fn move_test() {
+        let deque = deque_of(&[0, 1, 2]);
+        let mut cursor = deque.cursor_back();
+        assert_eq!(cursor.index(), Some(2));
+        cursor.move_next();
+        assert!(cursor.current().is_none());
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(*cursor.current().unwrap(), 2);
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(*cursor.current().unwrap(), 0);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(2));
+
+        let empty: Deque<i32> = Deque::new();
+        let mut cursor = empty.cursor_front();
+        assert!(cursor.current().is_none());
+        cursor.move_next();
+        cursor.move_prev();
+        assert!(cursor.current().is_none());
+    }
+
+    #[test]
+    fn insert_test() {
+        let mut deque = Deque::new();
+        let mut cursor = deque.cursor_front_mut();
+        cursor.insert_before(2);  // Ghost: at the tail.
+        cursor.insert_after(0);   // Ghost: at the head.
+        cursor.move_next();
+        assert_eq!(*cursor.current().unwrap(), 0);
+        cursor.insert_after(1);
+        cursor.insert_before(-1);
+        assert_eq!(cursor.index(), Some(1));
+        cursor.move_prev();
+        assert_eq!(*cursor.current().unwrap(), -1);
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(*cursor.current().unwrap(), 2);
+        cursor.insert_after(3);
+        drop(cursor);
+        assert_eq!(contents(&deque), vec![-1, 0, 1, 2, 3]);
+        // The cursor was on 2, but is gone, so 2 can be popped.
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_back(), Some(2));
+        assert_eq!(deque.pop(), Some(-1));
+    }
+
+    #[test]
+    fn remove_test() {
+        let mut deque = deque_of(&[0, 1, 2, 3]);
+        let mut cursor = deque.cursor_front_mut();
+        cursor.move_next();
+        *cursor.current().unwrap() = 10;
+        assert_eq!(cursor.remove_current(), Some(10));
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(*cursor.current().unwrap(), 2);
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(0));
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(3));
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.remove_current(), None);
+        drop(cursor);
+        assert_eq!(contents(&deque), vec![2]);
+        let mut cursor = deque.cursor_back_mut();
+        assert_eq!(cursor.remove_current(), Some(2));
+        drop(cursor);
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop(), None);
+        assert_eq!(deque.pop_back(), None);
+    }
+
+    #[test]
+    fn split_test() {
+        let mut deque = deque_of(&[0, 1, 2, 3, 4]);
+        let mut cursor = deque.cursor_front_mut();
+        assert_eq!(contents(&cursor.split_before()), vec![]);
+        cursor.move_next();
+        cursor.move_next();
+        let front = cursor.split_before();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(*cursor.current().unwrap(), 2);
+        drop(cursor);
+        assert_eq!(contents(&front), vec![0, 1]);
+        assert_eq!(contents(&deque), vec![2, 3, 4]);
+        let mut cursor = deque.cursor_back_mut();
+        cursor.move_next();
+        assert_eq!(contents(&cursor.split_before()), vec![2, 3, 4]);
+        drop(cursor);
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn splice_test() {
+        let mut deque = deque_of(&[0, 3]);
+        let mut cursor = deque.cursor_front_mut();
+        cursor.splice_after(deque_of(&[1, 2]));
+        cursor.splice_after(Deque::new());
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_prev();
+        cursor.splice_after(deque_of(&[-2, -1]));
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        cursor.splice_after(deque_of(&[4]));
+        drop(cursor);
+        assert_eq!(contents(&deque), vec![-2, -1, 0, 1, 2, 3, 4]);
+        let mut empty = Deque::new();
+        empty.cursor_front_mut().splice_after(deque_of(&[5, 6]));
+        assert_eq!(contents(&empty), vec![5, 6]);
+        assert_eq!(empty.pop_back(), Some(6));
+    }
+
+    #[test]
+    fn cursor_at_test() {
+        let mut deque = deque_of(&[0, 1, 2, 3, 4]);
+        for i in 0..5 {
+            let cursor = deque.cursor_at(i).unwrap();
+            assert_eq!(cursor.index(), Some(i));
+            assert_eq!(*cursor.current().unwrap(), i as i32);
+        }
+        assert!(deque.cursor_at(5).is_none());
+        let mut cursor = deque.cursor_at_mut(3).unwrap();
+        assert_eq!(cursor.index(), Some(3));
+        assert_eq!(cursor.remove_current(), Some(3));
+        drop(cursor);
+        assert!(deque.cursor_at_mut(4).is_none());
+        assert!(Deque::<i32>::new().cursor_at(0).is_none());
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut deque: Deque<i32> = Deque::new();
+        let mut cursor = deque.cursor_front_mut();
+        for i in 0..100000 {
+            cursor.insert_before(i);
+        }
+        let front = cursor.split_before();
+        assert_eq!(front.len(), 100000);
+        drop(front);
+        drop(cursor);
+        assert!(deque.is_empty());
+    }
+}
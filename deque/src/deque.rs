@@ -1,25 +1,43 @@
-use std::rc::Rc;
-use std::cell::RefCell;
-struct Deque<E> {
-    head: Option<Rc<RefCell<DequeNode<E>>>>,
-    tail: Option<Rc<RefCell<DequeNode<E>>>>,
-    size: usize,
+use alloc::rc::Rc;
+use core::cell::{Ref, RefCell, RefMut};
+use core::fmt::{Display, Formatter};
+use core::marker::PhantomData;
+use core::mem;
+use crate::double_ended::DoubleEnded;
+
+/// Doubly-linked deque. Usable without `std`, as it only needs `alloc`.
+///
+/// ```
+/// use rust_dust_deque::deque::Deque;
+///
+/// let mut deque = Deque::new();
+/// deque.push(2);
+/// deque.push(1);
+/// deque.push_back(3);
+/// assert_eq!(deque.len(), 3);
+/// assert_eq!(deque.pop_back(), Some(3));
+/// assert_eq!(deque.pop(), Some(1));
+/// ```
+pub struct Deque<E> {
+    pub(crate) head: Option<Rc<RefCell<DequeNode<E>>>>,
+    pub(crate) tail: Option<Rc<RefCell<DequeNode<E>>>>,
+    pub(crate) size: usize,
 }
 
-struct DequeNode<E> {
-    next: Option<Rc<RefCell<DequeNode<E>>>>,
-    prev: Option<Rc<RefCell<DequeNode<E>>>>,
-    elem: E,
+pub(crate) struct DequeNode<E> {
+    pub(crate) next: Option<Rc<RefCell<DequeNode<E>>>>,
+    pub(crate) prev: Option<Rc<RefCell<DequeNode<E>>>>,
+    pub(crate) elem: E,
 }
 
 impl<E> Deque<E> {
-
-    fn new() -> Self {
+    /// Create an empty deque. Does not allocate.
+    pub fn new() -> Self {
         Deque { head: None, tail: None, size: 0 }
     }
 
     /// Insert at the head of the deque.
-    fn push(&mut self, elem: E) {
+    pub fn push(&mut self, elem: E) {
         match self.head.take() {
             None => {
                 let new_head = Rc::new(RefCell::new(DequeNode { next: None, prev: None, elem }));
@@ -37,7 +55,7 @@ impl<E> Deque<E> {
     }
 
     /// Pop off the head of the deque.
-    fn pop(&mut self) -> Option<E> {
+    pub fn pop(&mut self) -> Option<E> {
         self.head.take().map (|old_head| {
             self.head = old_head.borrow_mut().next.take();
             match &self.head {
@@ -56,7 +74,7 @@ impl<E> Deque<E> {
      }
 
     /// Insert at the back of the queue
-    fn push_back(&mut self, elem: E) {
+    pub fn push_back(&mut self, elem: E) {
         match self.tail.take() {
             None => {
                 let new_node = Rc::new(RefCell::new(DequeNode { next: None, prev: None, elem }));
@@ -74,7 +92,7 @@ impl<E> Deque<E> {
     }
 
     /// Pop off the back of the queue.
-    fn pop_back(&mut self) -> Option<E> {
+    pub fn pop_back(&mut self) -> Option<E> {
         self.tail.take().map (|old_tail| {
             self.tail = old_tail.borrow_mut().prev.take();
             match &self.tail {
@@ -91,6 +109,259 @@ impl<E> Deque<E> {
             Rc::try_unwrap(old_tail).ok().unwrap().into_inner().elem
         })
     }
+
+    /// Number of elements in the deque.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the deque has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The head element, if any, without removing it.
+    pub fn front(&self) -> Option<Ref<'_, E>> {
+        self.head.as_ref().map(|node| Ref::map(node.borrow(), |node| &node.elem))
+    }
+
+    /// The tail element, if any, without removing it.
+    pub fn back(&self) -> Option<Ref<'_, E>> {
+        self.tail.as_ref().map(|node| Ref::map(node.borrow(), |node| &node.elem))
+    }
+
+    /// Mutable head element, if any, without removing it.
+    pub fn front_mut(&mut self) -> Option<RefMut<'_, E>> {
+        self.head.as_ref().map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.elem))
+    }
+
+    /// Mutable tail element, if any, without removing it.
+    pub fn back_mut(&mut self) -> Option<RefMut<'_, E>> {
+        self.tail.as_ref().map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.elem))
+    }
+
+    /// Iterate over the elements, head to tail or, with `rev()`, tail to head, without consuming them.
+    /// ```
+    /// use rust_dust_deque::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// for i in 0..3 {
+    ///     deque.push_back(i);
+    /// }
+    /// assert_eq!(deque.iter().map(|elem| *elem).collect::<Vec<_>>(), vec![0, 1, 2]);
+    /// assert_eq!(deque.iter().rev().map(|elem| *elem).collect::<Vec<_>>(), vec![2, 1, 0]);
+    /// assert_eq!(deque.len(), 3);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, E> {
+        // SAFETY: the iterator holds on to the borrow of `self`.
+        let (front, back) = unsafe { (node_ref(&self.head), node_ref(&self.tail)) };
+        Iter { front, back, remaining: self.size, _deque: PhantomData }
+    }
+
+    /// Iterate over mutable references to the elements, head to tail or, with `rev()`, tail to head.
+    pub fn iter_mut(&mut self) -> IterMut<'_, E> {
+        // SAFETY: the iterator holds on to the borrow of `self`.
+        let (front, back) = unsafe { (node_ref(&self.head), node_ref(&self.tail)) };
+        IterMut { front, back, remaining: self.size, _deque: PhantomData }
+    }
+
+    /// The element at `index` from the head, if there is one. Walks from the closer end,
+    /// so it takes up to `len() / 2` steps.
+    pub fn get(&self, index: usize) -> Option<Ref<'_, E>> {
+        self.node_at(index).map(|node| Ref::map(node.borrow(), |node| &node.elem))
+    }
+
+    /// Mutable element at `index` from the head, if there is one. Walks from the closer end.
+    pub fn get_mut(&mut self, index: usize) -> Option<RefMut<'_, E>> {
+        self.node_at(index).map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.elem))
+    }
+
+    /// Insert an element at `index`, shifting the elements after it towards the tail.
+    /// `index` may equal `len()`, to insert at the tail. If it is greater, the element is given
+    /// back in `Err`. Walks from the closer end.
+    pub fn insert(&mut self, index: usize, elem: E) -> Result<(), E> {
+        if index == self.size {
+            self.push_back(elem);
+            return Ok(());
+        }
+        match self.cursor_at_mut(index) {
+            None => Err(elem),
+            Some(mut cursor) => {
+                cursor.insert_before(elem);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove the element at `index` and return it, or `None` if there is no such element.
+    /// Walks from the closer end.
+    pub fn remove(&mut self, index: usize) -> Option<E> {
+        self.cursor_at_mut(index).and_then(|mut cursor| cursor.remove_current())
+    }
+
+    /// Swap the elements at indices `i` and `j`. The nodes stay where they are; only the elements move.
+    pub fn swap(&mut self, i: usize, j: usize) -> Result<(), IndexOutOfRange> {
+        let a = self.node_at(i).ok_or(IndexOutOfRange { index: i, len: self.size })?;
+        let b = self.node_at(j).ok_or(IndexOutOfRange { index: j, len: self.size })?;
+        if i != j {
+            mem::swap(&mut a.borrow_mut().elem, &mut b.borrow_mut().elem);
+        }
+        Ok(())
+    }
+
+    /// Node at `index`, walking from the closer end.
+    fn node_at(&self, index: usize) -> Option<&RefCell<DequeNode<E>>> {
+        if index >= self.size {
+            return None;
+        }
+        // SAFETY: the returned reference borrows `self`.
+        unsafe {
+            if index < self.size / 2 {
+                let mut node = node_ref(&self.head);
+                for _ in 0..index {
+                    node = node_ref(&node?.borrow().next);
+                }
+                node
+            } else {
+                let mut node = node_ref(&self.tail);
+                for _ in index..self.size - 1 {
+                    node = node_ref(&node?.borrow().prev);
+                }
+                node
+            }
+        }
+    }
+}
+
+/// Handle to a node, for the O(1) node operations below. Whoever holds one keeps the node alive,
+/// so it must be dropped before the node is popped: `pop()` expects to hold the only reference.
+pub(crate) type NodeRef<E> = Rc<RefCell<DequeNode<E>>>;
+
+/// Node operations, for structures that keep handles to the nodes, like the caches in `cache.rs`.
+/// The nodes passed in must belong to this deque.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+impl<E> Deque<E> {
+    /// Insert at the back of the deque, and return the new node.
+    pub(crate) fn push_back_node(&mut self, elem: E) -> NodeRef<E> {
+        self.push_back(elem);
+        self.tail.clone().unwrap()
+    }
+
+    /// Insert at the head of the deque, and return the new node.
+    pub(crate) fn push_front_node(&mut self, elem: E) -> NodeRef<E> {
+        self.push(elem);
+        self.head.clone().unwrap()
+    }
+
+    /// Insert right after `at`, and return the new node.
+    pub(crate) fn insert_after_node(&mut self, at: &NodeRef<E>, elem: E) -> NodeRef<E> {
+        let next = at.borrow_mut().next.take();
+        let node = Rc::new(RefCell::new(DequeNode { next: next.clone(), prev: Some(at.clone()), elem }));
+        match next {
+            None => self.tail = Some(node.clone()),
+            Some(next) => next.borrow_mut().prev = Some(node.clone()),
+        }
+        at.borrow_mut().next = Some(node.clone());
+        self.size += 1;
+        node
+    }
+
+    /// Take `node` out of the deque, without freeing it, so that it can be linked again.
+    pub(crate) fn unlink_node(&mut self, node: &NodeRef<E>) {
+        let prev = node.borrow_mut().prev.take();
+        let next = node.borrow_mut().next.take();
+        match &prev {
+            None => self.head = next.clone(),
+            Some(prev) => prev.borrow_mut().next = next.clone(),
+        }
+        match next {
+            None => self.tail = prev,
+            Some(next) => next.borrow_mut().prev = prev,
+        }
+        self.size -= 1;
+    }
+
+    /// Link an unlinked node at the back of the deque.
+    pub(crate) fn link_back_node(&mut self, node: NodeRef<E>) {
+        match self.tail.take() {
+            None => self.head = Some(node.clone()),
+            Some(old_tail) => {
+                node.borrow_mut().prev = Some(old_tail.clone());
+                old_tail.borrow_mut().next = Some(node.clone());
+            }
+        }
+        self.tail = Some(node);
+        self.size += 1;
+    }
+
+    /// Move `node` to the back of the deque.
+    pub(crate) fn move_node_to_back(&mut self, node: &NodeRef<E>) {
+        self.unlink_node(node);
+        self.link_back_node(node.clone());
+    }
+
+    /// Remove `node` from the deque and return its element. `node` must be the last handle to it.
+    pub(crate) fn remove_node(&mut self, node: NodeRef<E>) -> E {
+        self.unlink_node(&node);
+        Rc::try_unwrap(node).ok().expect("node still has other handles").into_inner().elem
+    }
+}
+
+/// Error returned when an index is not less than the deque's length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOutOfRange {
+    pub index: usize,
+    pub len: usize,
+}
+
+impl Display for IndexOutOfRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "index {} out of range for deque of length {}", self.index, self.len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IndexOutOfRange {}
+
+/// Reference to a node that lives as long as the deque's borrow, rather than the link's.
+/// A link inside a node can only be read through a short-lived `Ref` on the node, which would
+/// otherwise keep the iterators and `node_at()` from holding on to the next node.
+///
+/// # Safety
+/// `link` must belong to a deque that stays borrowed for `'a`. Nodes are only unlinked and freed
+/// by methods that take `&mut self`, so while the borrow lasts, every node stays where it is.
+unsafe fn node_ref<'a, E>(link: &Option<Rc<RefCell<DequeNode<E>>>>) -> Option<&'a RefCell<DequeNode<E>>> {
+    link.as_ref().map(|rc| unsafe { &*Rc::as_ptr(rc) })
+}
+
+impl<E> Default for Deque<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> DoubleEnded for Deque<E> {
+    type Elem = E;
+
+    fn push(&mut self, elem: E) {
+        Deque::push(self, elem)
+    }
+
+    fn pop(&mut self) -> Option<E> {
+        Deque::pop(self)
+    }
+
+    fn push_back(&mut self, elem: E) {
+        Deque::push_back(self, elem)
+    }
+
+    fn pop_back(&mut self) -> Option<E> {
+        Deque::pop_back(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
 }
 
 impl<E> Iterator for Deque<E> {
@@ -107,6 +378,93 @@ impl <E> DoubleEndedIterator for Deque<E> {
     }
 }
 
+/// Borrowing iterator, returned by `Deque::iter()`. Yields `Ref`s, since the elements live in `RefCell`s.
+pub struct Iter<'a, E> {
+    front: Option<&'a RefCell<DequeNode<E>>>,
+    back: Option<&'a RefCell<DequeNode<E>>>,
+    // The two ends meet when this drops to zero, rather than when they point to the same node.
+    remaining: usize,
+    _deque: PhantomData<&'a Deque<E>>,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = Ref<'a, E>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.front.map(|node| {
+            // SAFETY: `_deque` holds the borrow of the deque.
+            self.front = unsafe { node_ref(&node.borrow().next) };
+            Ref::map(node.borrow(), |node| &node.elem)
+        })
+    }
+}
+
+impl<E> DoubleEndedIterator for Iter<'_, E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.back.map(|node| {
+            // SAFETY: `_deque` holds the borrow of the deque.
+            self.back = unsafe { node_ref(&node.borrow().prev) };
+            Ref::map(node.borrow(), |node| &node.elem)
+        })
+    }
+}
+
+/// Mutably borrowing iterator, returned by `Deque::iter_mut()`. Yields `RefMut`s.
+pub struct IterMut<'a, E> {
+    front: Option<&'a RefCell<DequeNode<E>>>,
+    back: Option<&'a RefCell<DequeNode<E>>>,
+    remaining: usize,
+    _deque: PhantomData<&'a mut Deque<E>>,
+}
+
+impl<'a, E> Iterator for IterMut<'a, E> {
+    type Item = RefMut<'a, E>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.front.map(|node| {
+            // Follow the link before the node is borrowed mutably for as long as the caller likes.
+            // SAFETY: `_deque` holds the borrow of the deque.
+            self.front = unsafe { node_ref(&node.borrow().next) };
+            RefMut::map(node.borrow_mut(), |node| &mut node.elem)
+        })
+    }
+}
+
+impl<E> DoubleEndedIterator for IterMut<'_, E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.back.map(|node| {
+            // SAFETY: `_deque` holds the borrow of the deque.
+            self.back = unsafe { node_ref(&node.borrow().prev) };
+            RefMut::map(node.borrow_mut(), |node| &mut node.elem)
+        })
+    }
+}
+
+impl<'a, E> IntoIterator for &'a Deque<E> {
+    type Item = Ref<'a, E>;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// There is no `IntoIterator for &mut Deque`: since `Deque` is itself an `Iterator`, so is `&mut Deque`,
+// which makes it `IntoIterator` already, popping the elements.
+
 impl<E> Drop for Deque<E> {
     fn drop(&mut self) {
         let mut head = self.head.take();
@@ -117,7 +475,7 @@ impl<E> Drop for Deque<E> {
     }
 }
 
-
+#[cfg(test)]
 mod tests {
     use crate::deque::Deque;
     #[test]
@@ -217,4 +575,139 @@ mod tests {
             deque.push(i);
         }
     }
+
+    #[test]
+    fn peek_test() {
+        let mut deque: Deque<String> = Deque::new();
+        assert!(deque.front().is_none());
+        assert!(deque.back_mut().is_none());
+        deque.push("a".to_string());
+        assert_eq!(*deque.front().unwrap(), "a");
+        assert_eq!(*deque.back().unwrap(), "a");
+        deque.push_back("b".to_string());
+        deque.front_mut().unwrap().push('1');
+        deque.back_mut().unwrap().push('2');
+        assert_eq!(*deque.front().unwrap(), "a1");
+        assert_eq!(*deque.back().unwrap(), "b2");
+        // Shared borrows of the same element can coexist.
+        let (front, back) = (deque.front().unwrap(), deque.back().unwrap());
+        assert_eq!(format!("{}{}", *front, *back), "a1b2");
+        drop((front, back));
+        assert_eq!(deque.len(), 2);
+        assert_eq!(deque.pop(), Some("a1".to_string()));
+        assert_eq!(deque.pop(), Some("b2".to_string()));
+    }
+
+    #[test]
+    fn borrow_iter_test() {
+        let mut deque: Deque<i32> = Deque::new();
+        assert!(deque.iter().next().is_none());
+        assert!(deque.iter().next_back().is_none());
+        for i in 0..10 {
+            deque.push_back(i);
+        }
+        assert_eq!(deque.iter().map(|elem| *elem).collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(deque.iter().rev().map(|elem| *elem).collect::<Vec<_>>(), (0..10).rev().collect::<Vec<_>>());
+        // Both ends at once: they must meet in the middle without yielding an element twice.
+        let mut iter = deque.iter();
+        let mut seen = Vec::new();
+        while let Some(front) = iter.next() {
+            seen.push(*front);
+            if let Some(back) = iter.next_back() {
+                seen.push(*back);
+            }
+        }
+        assert_eq!(seen, vec![0, 9, 1, 8, 2, 7, 3, 6, 4, 5]);
+        let mut count = 0;
+        for _ in &deque {
+            count += 1;
+        }
+        assert_eq!(count, 10);
+        assert_eq!(deque.len(), 10);
+    }
+
+    #[test]
+    fn borrow_iter_mut_test() {
+        let mut deque: Deque<i32> = Deque::new();
+        for i in 0..9 {
+            deque.push_back(i);
+        }
+        // Hold all the guards at once.
+        let mut guards = deque.iter_mut().collect::<Vec<_>>();
+        for guard in guards.iter_mut() {
+            **guard *= 10;
+        }
+        drop(guards);
+        let mut iter = deque.iter_mut();
+        while let (Some(mut front), Some(mut back)) = (iter.next(), iter.next_back()) {
+            *front += 1;
+            *back += 2;
+        }
+        for mut elem in deque.iter_mut() {
+            *elem += 100;
+        }
+        assert_eq!(deque.collect::<Vec<_>>(), vec![101, 111, 121, 131, 140, 152, 162, 172, 182]);
+    }
+
+    #[test]
+    fn index_test() {
+        let mut deque: Deque<i32> = Deque::new();
+        assert!(deque.get(0).is_none());
+        assert_eq!(deque.insert(1, 1), Err(1));
+        assert_eq!(deque.insert(0, 1), Ok(()));
+        assert_eq!(deque.insert(1, 3), Ok(()));
+        assert_eq!(deque.insert(1, 2), Ok(()));
+        assert_eq!(deque.insert(0, 0), Ok(()));
+        for i in 4..10 {
+            assert_eq!(deque.insert(i as usize, i), Ok(()));
+        }
+        for i in 0..10 {
+            assert_eq!(*deque.get(i).unwrap(), i as i32);
+        }
+        assert!(deque.get(10).is_none());
+        *deque.get_mut(7).unwrap() *= 10;
+        *deque.get_mut(2).unwrap() *= 10;
+        assert!(deque.get_mut(10).is_none());
+        assert_eq!(deque.remove(7), Some(70));
+        assert_eq!(deque.remove(2), Some(20));
+        assert_eq!(deque.remove(8), None);
+        assert_eq!(deque.remove(0), Some(0));
+        assert_eq!(deque.remove(6), Some(9));
+        assert_eq!(deque.len(), 6);
+        assert_eq!(deque.iter().map(|elem| *elem).collect::<Vec<_>>(), vec![1, 3, 4, 5, 6, 8]);
+        assert_eq!(deque.pop_back(), Some(8));
+        assert_eq!(deque.pop(), Some(1));
+    }
+
+    #[test]
+    fn swap_test() {
+        use crate::deque::IndexOutOfRange;
+        let mut deque: Deque<String> = Deque::new();
+        for i in 0..5 {
+            deque.push_back(i.to_string());
+        }
+        assert_eq!(deque.swap(0, 4), Ok(()));
+        assert_eq!(deque.swap(1, 3), Ok(()));
+        assert_eq!(deque.swap(2, 2), Ok(()));
+        assert_eq!(deque.swap(1, 5), Err(IndexOutOfRange { index: 5, len: 5 }));
+        assert_eq!(deque.swap(7, 0), Err(IndexOutOfRange { index: 7, len: 5 }));
+        assert_eq!(deque.collect::<Vec<_>>(), vec!["4", "3", "2", "1", "0"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn index_out_of_range_error_test() {
+        use crate::deque::IndexOutOfRange;
+        fn swap_ends(deque: &mut Deque<i32>) -> Result<(), Box<dyn std::error::Error>> {
+            deque.swap(0, 3)?;
+            Ok(())
+        }
+        let mut deque = Deque::new();
+        for i in 0..3 {
+            deque.push_back(i);
+        }
+        let err = swap_ends(&mut deque).unwrap_err();
+        assert_eq!(err.to_string(), "index 3 out of range for deque of length 3");
+        assert_eq!(IndexOutOfRange { index: 5, len: 0 }.to_string(), "index 5 out of range for deque of length 0");
+    }
 }
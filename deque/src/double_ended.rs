@@ -0,0 +1,172 @@
+/// Collection that can add and remove elements at both ends: the operations that all the deques
+/// in this crate have in common. Code that is generic over `DoubleEnded` works with any of them,
+/// so the implementation can be picked with a type parameter.
+///
+/// ```
+/// use rust_dust_deque::deque::Deque;
+/// use rust_dust_deque::double_ended::DoubleEnded;
+/// use rust_dust_deque::ring::RingDeque;
+///
+/// // Rotate the elements one step from the back to the front, `n` times.
+/// fn rotate<D: DoubleEnded>(deque: &mut D, n: usize) {
+///     for _ in 0..n {
+///         if let Some(elem) = deque.pop_back() {
+///             deque.push(elem);
+///         }
+///     }
+/// }
+///
+/// let mut linked = Deque::new();
+/// let mut ring = RingDeque::new();
+/// for i in 0..5 {
+///     linked.push_back(i);
+///     ring.push_back(i);
+/// }
+/// rotate(&mut linked, 2);
+/// rotate(&mut ring, 2);
+/// assert_eq!(linked.pop(), Some(3));
+/// assert_eq!(ring.pop(), Some(3));
+/// ```
+pub trait DoubleEnded {
+    type Elem;
+
+    /// Insert at the front.
+    fn push(&mut self, elem: Self::Elem);
+
+    /// Remove from the front.
+    fn pop(&mut self) -> Option<Self::Elem>;
+
+    /// Insert at the back.
+    fn push_back(&mut self, elem: Self::Elem);
+
+    /// Remove from the back.
+    fn pop_back(&mut self) -> Option<Self::Elem>;
+
+    /// Number of elements.
+    fn len(&self) -> usize;
+
+    /// `true` if there are no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// One test suite for all implementations.
+#[cfg(test)]
+mod tests {
+    use crate::deque::Deque;
+    use crate::double_ended::DoubleEnded;
+    use crate::raw::RawDeque;
+    use crate::ring::RingDeque;
+    use crate::slab::SlabList;
+
+    fn front_test<D: DoubleEnded<Elem=i32> + Default>() {
+        let mut deque = D::default();
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop(), None);
+        for i in 0..100 {
+            deque.push(i);
+            assert_eq!(deque.len(), (i + 1) as usize);
+        }
+        for i in (0..100).rev() {
+            assert_eq!(deque.pop(), Some(i));
+            assert_eq!(deque.len(), i as usize);
+        }
+        assert_eq!(deque.pop(), None);
+        assert_eq!(deque.pop_back(), None);
+    }
+
+    fn back_test<D: DoubleEnded<Elem=i32> + Default>() {
+        let mut deque = D::default();
+        assert_eq!(deque.pop_back(), None);
+        for i in 0..100 {
+            deque.push_back(i);
+        }
+        for i in (0..100).rev() {
+            assert_eq!(deque.pop_back(), Some(i));
+        }
+        assert!(deque.is_empty());
+    }
+
+    fn queue_test<D: DoubleEnded<Elem=i32> + Default>() {
+        // FIFO in both directions, interleaved, so that a ring buffer wraps around.
+        let mut deque = D::default();
+        let mut next_in = 0;
+        let mut next_out = 0;
+        for round in 0..50 {
+            for _ in 0..round % 7 + 1 {
+                deque.push_back(next_in);
+                next_in += 1;
+            }
+            for _ in 0..round % 5 {
+                if let Some(elem) = deque.pop() {
+                    assert_eq!(elem, next_out);
+                    next_out += 1;
+                }
+            }
+            assert_eq!(deque.len(), (next_in - next_out) as usize);
+        }
+        while let Some(elem) = deque.pop() {
+            assert_eq!(elem, next_out);
+            next_out += 1;
+        }
+        assert_eq!(next_out, next_in);
+        for i in 0..10 {
+            deque.push(i);
+        }
+        for i in 0..10 {
+            assert_eq!(deque.pop_back(), Some(i));
+        }
+    }
+
+    fn mixed_test<D: DoubleEnded<Elem=i32> + Default>() {
+        let mut deque = D::default();
+        for i in 0..10 {
+            if i % 2 == 0 {
+                deque.push(i);
+            } else {
+                deque.push_back(i);
+            }
+        }
+        for expected in [8, 6, 4, 2, 0, 1, 3, 5, 7] {
+            assert_eq!(deque.pop(), Some(expected));
+        }
+        assert_eq!(deque.pop_back(), Some(9));
+        assert!(deque.is_empty());
+    }
+
+    fn drop_test<D: DoubleEnded<Elem=i32> + Default>() {
+        let mut deque = D::default();
+        for i in 0..100000 {
+            deque.push(i);
+        }
+    }
+
+    fn suite<D: DoubleEnded<Elem=i32> + Default>() {
+        front_test::<D>();
+        back_test::<D>();
+        queue_test::<D>();
+        mixed_test::<D>();
+        drop_test::<D>();
+    }
+
+    #[test]
+    fn deque_test() {
+        suite::<Deque<i32>>();
+    }
+
+    #[test]
+    fn raw_deque_test() {
+        suite::<RawDeque<i32>>();
+    }
+
+    #[test]
+    fn ring_deque_test() {
+        suite::<RingDeque<i32>>();
+    }
+
+    #[test]
+    fn slab_list_test() {
+        suite::<SlabList<i32>>();
+    }
+}
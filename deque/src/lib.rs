@@ -0,0 +1,28 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+//mod r#box;
+//mod rc;
+#[cfg(feature = "alloc")]
+pub mod deque;
+#[cfg(feature = "alloc")]
+pub mod cursor;
+#[cfg(feature = "alloc")]
+pub mod raw;
+pub mod double_ended;
+#[cfg(feature = "alloc")]
+pub mod ring;
+#[cfg(feature = "alloc")]
+pub mod sort;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod blocking;
+#[cfg(feature = "alloc")]
+pub mod slab;
+#[cfg(feature = "alloc")]
+pub mod persistent;
+#[cfg(feature = "alloc")]
+pub mod window;
@@ -1,5 +0,0 @@
-//mod r#box;
-//mod rc;
-mod deque;
-
-fn main() {}
@@ -0,0 +1,439 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{Cell, OnceCell};
+use core::fmt::{Debug, Formatter};
+
+/// Persistent (immutable) deque. Pushes and pops at either end leave `self` intact and return
+/// a new version of the deque, which shares most of its structure with the old version.
+///
+/// This is Okasaki's _banker's deque_: the front of the deque is one list with the head element
+/// first, and the back is another with the tail element first, so that both ends are O(1) to reach.
+/// When one list runs low, there would be no cheap way to reach the elements at the end of the other,
+/// so the lists are kept _balanced_: neither may have more than `BALANCE * len + 1` elements,
+/// where `len` is the length of the other. When an operation breaks that, both lists are rebuilt
+/// with half of the elements each.
+///
+/// A rebuild is O(n), and with eager rebuilding, a version that is one pop away from one could be
+/// popped again and again, paying O(n) each time. So the lists are _lazy streams_: a rebuild only
+/// sets up suspended computations, and each cell of a stream is computed the first time it is
+/// looked at, and then kept. The expensive part, reversing half of the elements, is only looked
+/// at after about `n / 4` pops from the rebuilt end, and whatever has been computed is shared by
+/// all versions that share the stream. That makes pushes and pops amortized O(1), even when old
+/// versions are used again.
+///
+/// The elements are kept in `Rc`s, so that rebuilding moves `Rc`s rather than cloning elements.
+///
+/// ```
+/// use rust_dust_deque::persistent::PersistentDeque;
+///
+/// let empty = PersistentDeque::new();
+/// let one = empty.push(1);
+/// let two = one.push_back(2);
+/// let (head, rest) = two.pop().unwrap();
+/// assert_eq!(*head, 1);
+/// assert_eq!(rest.front(), Some(&2));
+/// // Old versions are still valid.
+/// assert_eq!(two.len(), 2);
+/// assert_eq!(one.back(), Some(&1));
+/// assert!(empty.is_empty());
+/// ```
+pub struct PersistentDeque<E> {
+    // Head first.
+    front: Stream<E>,
+    front_len: usize,
+    // Tail first.
+    rear: Stream<E>,
+    rear_len: usize,
+}
+
+impl<E> PersistentDeque<E> {
+    /// How much longer one list may get than the other, before the deque is rebalanced.
+    pub const BALANCE: usize = 3;
+
+    /// Create an empty deque. Does not allocate.
+    pub fn new() -> Self {
+        PersistentDeque { front: Stream::empty(), front_len: 0, rear: Stream::empty(), rear_len: 0 }
+    }
+
+    /// New version of this deque with `elem` at the head.
+    pub fn push(&self, elem: E) -> Self {
+        let front = Stream::cons(Rc::new(elem), self.front.clone());
+        Self::balanced(front, self.front_len + 1, self.rear.clone(), self.rear_len)
+    }
+
+    /// The head element and the new version of this deque without it, or `None` if the deque is empty.
+    pub fn pop(&self) -> Option<(&E, Self)> {
+        match self.front.force() {
+            Some((elem, front)) => Some((elem, Self::balanced(front.clone(), self.front_len - 1, self.rear.clone(), self.rear_len))),
+            // Balanced, so the rear has at most one element.
+            None => self.rear.force().map(|(elem, _)| (&**elem, PersistentDeque::new())),
+        }
+    }
+
+    /// New version of this deque with `elem` at the tail.
+    pub fn push_back(&self, elem: E) -> Self {
+        let rear = Stream::cons(Rc::new(elem), self.rear.clone());
+        Self::balanced(self.front.clone(), self.front_len, rear, self.rear_len + 1)
+    }
+
+    /// The tail element and the new version of this deque without it, or `None` if the deque is empty.
+    pub fn pop_back(&self) -> Option<(&E, Self)> {
+        match self.rear.force() {
+            Some((elem, rear)) => Some((elem, Self::balanced(self.front.clone(), self.front_len, rear.clone(), self.rear_len - 1))),
+            // Balanced, so the front has at most one element.
+            None => self.front.force().map(|(elem, _)| (&**elem, PersistentDeque::new())),
+        }
+    }
+
+    /// The head element, if any. With an empty front, the rear has at most one element, which is it.
+    pub fn front(&self) -> Option<&E> {
+        self.front.force().or_else(|| self.rear.force()).map(|(elem, _)| &**elem)
+    }
+
+    /// The tail element, if any.
+    pub fn back(&self) -> Option<&E> {
+        self.rear.force().or_else(|| self.front.force()).map(|(elem, _)| &**elem)
+    }
+
+    /// Number of elements in this version of the deque.
+    pub fn len(&self) -> usize {
+        self.front_len + self.rear_len
+    }
+
+    /// `true` if this version of the deque has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the elements, head to tail. The back half has to be reversed first,
+    /// so this allocates a `Vec` of references to it.
+    pub fn iter(&self) -> Iter<'_, E> {
+        let mut rear = Vec::with_capacity(self.rear_len);
+        let mut curr = &self.rear;
+        while let Some((elem, rest)) = curr.force() {
+            rear.push(elem);
+            curr = rest;
+        }
+        Iter { front: &self.front, rear }
+    }
+
+    /// Deque made of `front` and `rear`, rebalanced if need be. The longer list keeps its first
+    /// half, and the rest of it is reversed onto the end of the other one, all lazily.
+    fn balanced(front: Stream<E>, front_len: usize, rear: Stream<E>, rear_len: usize) -> Self {
+        let len = front_len + rear_len;
+        if front_len > Self::BALANCE * rear_len + 1 {
+            let keep = len / 2;
+            PersistentDeque {
+                front: Stream::lazy(Suspension::Take(keep, front.clone())),
+                front_len: keep,
+                rear: Stream::lazy(Suspension::Append(rear, Stream::lazy(Suspension::ReverseDrop(keep, front)))),
+                rear_len: len - keep,
+            }
+        } else if rear_len > Self::BALANCE * front_len + 1 {
+            let keep = len / 2;
+            PersistentDeque {
+                front: Stream::lazy(Suspension::Append(front, Stream::lazy(Suspension::ReverseDrop(keep, rear.clone())))),
+                front_len: len - keep,
+                rear: Stream::lazy(Suspension::Take(keep, rear)),
+                rear_len: keep,
+            }
+        } else {
+            PersistentDeque { front, front_len, rear, rear_len }
+        }
+    }
+}
+
+/// Lazy list. Each cell is computed by its suspension the first time it is forced, and then kept,
+/// so that all the streams that share the cell share the work. `None` is the empty stream.
+struct Stream<E> {
+    cell: Option<Rc<StreamCell<E>>>,
+}
+
+/// A computed cell: the first element and the rest of the stream, or `None` at the end.
+type Forced<E> = Option<(Rc<E>, Stream<E>)>;
+
+struct StreamCell<E> {
+    forced: OnceCell<Forced<E>>,
+    // Taken when the cell is forced.
+    suspension: Cell<Option<Suspension<E>>>,
+}
+
+/// What a cell of a stream computes when forced. `Take` and `Append` only compute one cell at a time,
+/// and suspend the rest of the stream; `ReverseDrop` has to compute the entire stream at once.
+enum Suspension<E> {
+    /// The first `n` elements of a stream.
+    Take(usize, Stream<E>),
+    /// One stream followed by another.
+    Append(Stream<E>, Stream<E>),
+    /// The elements of a stream after the first `n`, in reverse order.
+    ReverseDrop(usize, Stream<E>),
+}
+
+impl<E> Stream<E> {
+    fn empty() -> Self {
+        Stream { cell: None }
+    }
+
+    /// Stream with `elem` in front of `rest`, computed already.
+    fn cons(elem: Rc<E>, rest: Stream<E>) -> Self {
+        let forced = OnceCell::from(Some((elem, rest)));
+        Stream { cell: Some(Rc::new(StreamCell { forced, suspension: Cell::new(None) })) }
+    }
+
+    fn lazy(suspension: Suspension<E>) -> Self {
+        Stream { cell: Some(Rc::new(StreamCell { forced: OnceCell::new(), suspension: Cell::new(Some(suspension)) })) }
+    }
+
+    /// The first element and the rest of the stream, computing them if this is the first time.
+    fn force(&self) -> Option<&(Rc<E>, Stream<E>)> {
+        let cell = self.cell.as_ref()?;
+        cell.forced.get_or_init(|| cell.suspension.take().expect("cell forced twice").run()).as_ref()
+    }
+}
+
+impl<E> Suspension<E> {
+    fn run(self) -> Forced<E> {
+        match self {
+            Suspension::Take(0, _) => None,
+            Suspension::Take(n, stream) => stream.force().map(|(elem, rest)| {
+                (elem.clone(), Stream::lazy(Suspension::Take(n - 1, rest.clone())))
+            }),
+            Suspension::Append(first, second) => match first.force() {
+                Some((elem, rest)) => Some((elem.clone(), Stream::lazy(Suspension::Append(rest.clone(), second)))),
+                None => second.force().cloned(),
+            },
+            Suspension::ReverseDrop(n, stream) => {
+                let mut curr = stream;
+                for _ in 0..n {
+                    curr = curr.force().expect("stream too short").1.clone();
+                }
+                let mut reversed = Stream::empty();
+                while let Some((elem, rest)) = curr.force().cloned() {
+                    reversed = Stream::cons(elem, reversed);
+                    curr = rest;
+                }
+                reversed.force().cloned()
+            }
+        }
+    }
+}
+
+/// Cloning a stream is O(1): it only clones the `Rc` of its first cell.
+impl<E> Clone for Stream<E> {
+    fn clone(&self) -> Self {
+        Stream { cell: self.cell.clone() }
+    }
+}
+
+impl<E> Drop for Stream<E> {
+    // Like in `PersistentStack`, the compiler-generated `drop()` would be recursive, so the computed
+    // cells are unlinked in a loop, until one is reached that is shared with another stream.
+    fn drop(&mut self) {
+        let mut curr = self.cell.take();
+        while let Some(rc_cell) = curr {
+            match Rc::try_unwrap(rc_cell) {
+                Ok(cell) => curr = cell.forced.into_inner().flatten().and_then(|(_, mut rest)| rest.cell.take()),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<E> Default for PersistentDeque<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cloning a version is O(1): it only clones the first cells of the two streams.
+impl<E> Clone for PersistentDeque<E> {
+    fn clone(&self) -> Self {
+        PersistentDeque { front: self.front.clone(), front_len: self.front_len, rear: self.rear.clone(), rear_len: self.rear_len }
+    }
+}
+
+impl<E: Debug> Debug for PersistentDeque<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Elements are pushed at the back in iteration order, so the first one ends up at the head.
+impl<E> FromIterator<E> for PersistentDeque<E> {
+    fn from_iter<I: IntoIterator<Item=E>>(iter: I) -> Self {
+        let mut deque = PersistentDeque::new();
+        for elem in iter {
+            deque = deque.push_back(elem);
+        }
+        deque
+    }
+}
+
+/// Borrowing iterator, returned by `PersistentDeque::iter()`.
+pub struct Iter<'a, E> {
+    front: &'a Stream<E>,
+    // Tail first, so popped from the end.
+    rear: Vec<&'a Rc<E>>,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.front.force() {
+            Some((elem, rest)) => {
+                self.front = rest;
+                Some(&**elem)
+            }
+            None => self.rear.pop().map(|elem| &**elem),
+        }
+    }
+}
+
+impl<'a, E> IntoIterator for &'a PersistentDeque<E> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use crate::persistent::{PersistentDeque, Stream};
+
+    fn assert_balanced<E>(deque: &PersistentDeque<E>) {
+        let (front, rear) = (deque.front_len, deque.rear_len);
+        assert!(front <= PersistentDeque::<E>::BALANCE * rear + 1, "front {front}, rear {rear}");
+        assert!(rear <= PersistentDeque::<E>::BALANCE * front + 1, "front {front}, rear {rear}");
+    }
+
+    #[test]
+    fn basics_test() {
+        let mut deque = PersistentDeque::new();
+        assert!(deque.pop().is_none());
+        assert!(deque.pop_back().is_none());
+        for i in 0..10 {
+            deque = deque.push(i).push_back(i);
+            assert_balanced(&deque);
+        }
+        assert_eq!(deque.len(), 20);
+        assert_eq!(deque.front(), Some(&9));
+        assert_eq!(deque.back(), Some(&9));
+        for i in (0..10).rev() {
+            let (head, rest) = deque.pop().unwrap();
+            assert_eq!(*head, i);
+            let (tail, rest) = rest.pop_back().unwrap();
+            assert_eq!(*tail, i);
+            assert_balanced(&rest);
+            deque = rest;
+        }
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn one_end_test() {
+        // Everything pushed at one end and popped at the other goes through rebalancing.
+        let mut deque = PersistentDeque::new();
+        for i in 0..1000 {
+            deque = deque.push(i);
+            assert_balanced(&deque);
+        }
+        for i in 0..1000 {
+            let (tail, rest) = deque.pop_back().unwrap();
+            assert_eq!(*tail, i);
+            assert_balanced(&rest);
+            deque = rest;
+        }
+        // A single element sits in either stack, and both ends find it.
+        let deque = PersistentDeque::new().push_back('a');
+        assert_eq!(deque.front(), Some(&'a'));
+        assert_eq!(*deque.pop().unwrap().0, 'a');
+        let deque = PersistentDeque::new().push('b');
+        assert_eq!(deque.back(), Some(&'b'));
+        assert_eq!(*deque.pop_back().unwrap().0, 'b');
+    }
+
+    #[test]
+    fn versions_test() {
+        let base = (0..100).collect::<PersistentDeque<_>>();
+        let shorter = base.pop().unwrap().1.pop_back().unwrap().1;
+        let longer = base.push(-1).push_back(100);
+        assert_eq!(base.iter().copied().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+        assert_eq!(shorter.iter().copied().collect::<Vec<_>>(), (1..99).collect::<Vec<_>>());
+        assert_eq!(longer.iter().copied().collect::<Vec<_>>(), (-1..101).collect::<Vec<_>>());
+        let copy = shorter.clone();
+        drop(shorter);
+        assert_eq!(copy.len(), 98);
+        assert_eq!(format!("{:?}", (1..4).collect::<PersistentDeque<_>>()), "[1, 2, 3]");
+        assert_eq!((&copy).into_iter().next(), Some(&1));
+    }
+
+    #[test]
+    fn lazy_rebalance_test() {
+        // 7 elements in front and 2 in the rear: one pop from the rear away from a rebalance.
+        let stream = |elems: &[i32]| elems.iter().rev().fold(Stream::empty(), |rest, elem| Stream::cons(Rc::new(*elem), rest));
+        let deque = PersistentDeque { front: stream(&[0, 1, 2, 3, 4, 5, 6]), front_len: 7, rear: stream(&[101, 100]), rear_len: 2 };
+        assert_balanced(&deque);
+        // Every pop of that version rebalances, but only sets up the reversal, without doing it.
+        for _ in 0..3 {
+            let (tail, rest) = deque.pop_back().unwrap();
+            assert_eq!(*tail, 101);
+            assert_eq!((rest.front_len, rest.rear_len), (4, 4));
+            // The rear starts with the old rear's element; the reversed ones come after it.
+            let (elem, reversed) = rest.rear.force().unwrap();
+            assert_eq!(**elem, 100);
+            assert!(reversed.cell.as_ref().unwrap().forced.get().is_none());
+            assert_eq!(rest.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 6, 100]);
+            assert!(reversed.cell.as_ref().unwrap().forced.get().is_some());
+        }
+    }
+
+    #[test]
+    fn versions_model_test() {
+        // Random operations on random old versions, checked against `VecDeque`.
+        use std::collections::VecDeque;
+        let mut state: u64 = 3;
+        let mut random = |modulus: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % modulus
+        };
+        let mut versions = vec![(PersistentDeque::new(), VecDeque::new())];
+        for i in 0..5000 {
+            let (deque, model) = &versions[random(versions.len() as u64) as usize];
+            let (mut deque, mut model) = (deque.clone(), model.clone());
+            match random(4) {
+                0 => {
+                    deque = deque.push(i);
+                    model.push_front(i);
+                }
+                1 => {
+                    deque = deque.push_back(i);
+                    model.push_back(i);
+                }
+                2 => if let Some((head, rest)) = deque.pop() {
+                    assert_eq!(Some(*head), model.pop_front());
+                    deque = rest;
+                },
+                _ => if let Some((tail, rest)) = deque.pop_back() {
+                    assert_eq!(Some(*tail), model.pop_back());
+                    deque = rest;
+                },
+            }
+            assert_balanced(&deque);
+            assert_eq!(deque.len(), model.len());
+            assert_eq!(deque.front(), model.front());
+            assert_eq!(deque.back(), model.back());
+            versions.push((deque, model));
+        }
+        for (deque, model) in &versions {
+            assert!(deque.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn drop_test() {
+        let deque = (0..100000).collect::<PersistentDeque<_>>();
+        let _version = deque.pop().unwrap().1;
+    }
+}
@@ -0,0 +1,467 @@
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use crate::double_ended::DoubleEnded;
+
+/// Doubly-linked deque with raw pointer links: same API as `Deque`, but its nodes are linked with
+/// `NonNull` pointers rather than `Rc<RefCell<...>>`, so there are no reference counts or borrow
+/// flags to maintain, and peeking and iterating return plain references.
+///
+/// ```
+/// use rust_dust_deque::raw::RawDeque;
+///
+/// let mut deque = RawDeque::new();
+/// deque.push(2);
+/// deque.push(1);
+/// deque.push_back(3);
+/// assert_eq!(deque.front(), Some(&1));
+/// assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+/// assert_eq!(deque.pop_back(), Some(3));
+/// assert_eq!(deque.pop(), Some(1));
+/// ```
+pub struct RawDeque<E> {
+    // Every node is allocated by `Box::new()` in `push()` or `push_back()`, leaked, and linked in
+    // exactly once. It is owned by the deque until `pop()` or `pop_back()` unlinks it and turns it
+    // back into a `Box` with `Box::from_raw()`.
+    head: Option<NonNull<RawNode<E>>>,
+    tail: Option<NonNull<RawNode<E>>>,
+    size: usize,
+    // Tells the compiler that we own the nodes and their elements, which matters to the drop checker.
+    _nodes: PhantomData<Box<RawNode<E>>>,
+}
+
+struct RawNode<E> {
+    next: Option<NonNull<RawNode<E>>>,
+    prev: Option<NonNull<RawNode<E>>>,
+    elem: E,
+}
+
+// SAFETY: the deque owns its nodes exclusively, just like a `Box` would, so it can be sent or shared
+// whenever its elements can. Raw pointers are neither `Send` nor `Sync` by default only as a precaution.
+unsafe impl<E: Send> Send for RawDeque<E> {}
+unsafe impl<E: Sync> Sync for RawDeque<E> {}
+
+impl<E> RawDeque<E> {
+    /// Create an empty deque. Does not allocate.
+    pub fn new() -> Self {
+        RawDeque { head: None, tail: None, size: 0, _nodes: PhantomData }
+    }
+
+    /// Insert at the head of the deque.
+    pub fn push(&mut self, elem: E) {
+        let new_head = NonNull::from(Box::leak(Box::new(RawNode { next: self.head, prev: None, elem })));
+        match self.head {
+            // SAFETY: `old_head` is a live node of this deque, which we borrow mutably.
+            Some(old_head) => unsafe { (*old_head.as_ptr()).prev = Some(new_head) },
+            None => self.tail = Some(new_head),
+        }
+        self.head = Some(new_head);
+        self.size += 1;
+    }
+
+    /// Pop off the head of the deque.
+    pub fn pop(&mut self) -> Option<E> {
+        self.head.map(|old_head| {
+            // SAFETY: `old_head` came from `Box::leak()` and is unlinked right here, so nothing
+            // points to it once we're done.
+            let old_head = unsafe { Box::from_raw(old_head.as_ptr()) };
+            self.head = old_head.next;
+            match self.head {
+                // SAFETY: `new_head` is a live node of this deque.
+                Some(new_head) => unsafe { (*new_head.as_ptr()).prev = None },
+                None => self.tail = None,
+            }
+            self.size -= 1;
+            old_head.elem
+        })
+    }
+
+    /// Insert at the back of the queue
+    pub fn push_back(&mut self, elem: E) {
+        let new_tail = NonNull::from(Box::leak(Box::new(RawNode { next: None, prev: self.tail, elem })));
+        match self.tail {
+            // SAFETY: `old_tail` is a live node of this deque, which we borrow mutably.
+            Some(old_tail) => unsafe { (*old_tail.as_ptr()).next = Some(new_tail) },
+            None => self.head = Some(new_tail),
+        }
+        self.tail = Some(new_tail);
+        self.size += 1;
+    }
+
+    /// Pop off the back of the queue.
+    pub fn pop_back(&mut self) -> Option<E> {
+        self.tail.map(|old_tail| {
+            // SAFETY: same as in `pop()`.
+            let old_tail = unsafe { Box::from_raw(old_tail.as_ptr()) };
+            self.tail = old_tail.prev;
+            match self.tail {
+                // SAFETY: `new_tail` is a live node of this deque.
+                Some(new_tail) => unsafe { (*new_tail.as_ptr()).next = None },
+                None => self.head = None,
+            }
+            self.size -= 1;
+            old_tail.elem
+        })
+    }
+
+    /// Number of elements in the deque.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the deque has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    // SAFETY, for the four methods below: the node is live, and the returned reference borrows
+    // the deque, so the node cannot be freed while the reference is in use.
+
+    /// The head element, if any, without removing it.
+    pub fn front(&self) -> Option<&E> {
+        self.head.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    /// The tail element, if any, without removing it.
+    pub fn back(&self) -> Option<&E> {
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    /// Mutable head element, if any, without removing it.
+    pub fn front_mut(&mut self) -> Option<&mut E> {
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    /// Mutable tail element, if any, without removing it.
+    pub fn back_mut(&mut self) -> Option<&mut E> {
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    /// Iterate over the elements, head to tail or, with `rev()`, tail to head, without consuming them.
+    pub fn iter(&self) -> Iter<'_, E> {
+        Iter { front: self.head, back: self.tail, remaining: self.size, _deque: PhantomData }
+    }
+
+    /// Iterate over mutable references to the elements, head to tail or, with `rev()`, tail to head.
+    pub fn iter_mut(&mut self) -> IterMut<'_, E> {
+        IterMut { front: self.head, back: self.tail, remaining: self.size, _deque: PhantomData }
+    }
+}
+
+impl<E> Default for RawDeque<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> DoubleEnded for RawDeque<E> {
+    type Elem = E;
+
+    fn push(&mut self, elem: E) {
+        RawDeque::push(self, elem)
+    }
+
+    fn pop(&mut self) -> Option<E> {
+        RawDeque::pop(self)
+    }
+
+    fn push_back(&mut self, elem: E) {
+        RawDeque::push_back(self, elem)
+    }
+
+    fn pop_back(&mut self) -> Option<E> {
+        RawDeque::pop_back(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+impl<E> Iterator for RawDeque<E> {
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop()
+    }
+}
+
+impl<E> DoubleEndedIterator for RawDeque<E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.pop_back()
+    }
+}
+
+impl<E> Drop for RawDeque<E> {
+    // Nothing is freed unless we free it, and popping frees one node at a time, without recursion.
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// Borrowing iterator, returned by `RawDeque::iter()`.
+pub struct Iter<'a, E> {
+    front: Option<NonNull<RawNode<E>>>,
+    back: Option<NonNull<RawNode<E>>>,
+    // The two ends meet when this drops to zero.
+    remaining: usize,
+    _deque: PhantomData<&'a RawDeque<E>>,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.front.map(|node| {
+            // SAFETY: the deque is borrowed for `'a`, so its nodes live at least as long.
+            let node = unsafe { &*node.as_ptr() };
+            self.front = node.next;
+            &node.elem
+        })
+    }
+}
+
+impl<E> DoubleEndedIterator for Iter<'_, E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.back.map(|node| {
+            // SAFETY: same as in `next()`.
+            let node = unsafe { &*node.as_ptr() };
+            self.back = node.prev;
+            &node.elem
+        })
+    }
+}
+
+/// Mutably borrowing iterator, returned by `RawDeque::iter_mut()`.
+pub struct IterMut<'a, E> {
+    front: Option<NonNull<RawNode<E>>>,
+    back: Option<NonNull<RawNode<E>>>,
+    remaining: usize,
+    _deque: PhantomData<&'a mut RawDeque<E>>,
+}
+
+impl<'a, E> Iterator for IterMut<'a, E> {
+    type Item = &'a mut E;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.front.map(|node| {
+            // SAFETY: the deque is borrowed mutably for `'a`, and `remaining` ensures that no element
+            // is returned twice. We never create a reference to a whole node, only to its fields,
+            // so the caller's `&mut` to one node's element doesn't overlap with our reading of
+            // another node's links.
+            unsafe {
+                self.front = (*node.as_ptr()).next;
+                &mut (*node.as_ptr()).elem
+            }
+        })
+    }
+}
+
+impl<E> DoubleEndedIterator for IterMut<'_, E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.back.map(|node| {
+            // SAFETY: same as in `next()`.
+            unsafe {
+                self.back = (*node.as_ptr()).prev;
+                &mut (*node.as_ptr()).elem
+            }
+        })
+    }
+}
+
+impl<'a, E> IntoIterator for &'a RawDeque<E> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Same tests as `Deque`'s, plus ownership tests.
+#[cfg(test)]
+mod tests {
+    use crate::raw::RawDeque;
+
+    // Miri is slow, so it gets smaller deques.
+    const BIG: i32 = if cfg!(miri) { 1000 } else { 100000 };
+
+    #[test]
+    fn test_front() {
+        let mut deque: RawDeque<i32> = RawDeque::new();
+        assert_eq!(deque.size, 0);
+        assert_eq!(deque.pop(), None);
+        assert_eq!(deque.size, 0);
+
+        for i in 0..10 {
+            deque.push(i);
+            assert_eq!(deque.size, (i + 1) as usize);
+        }
+        for i in (0..10).rev() {
+            assert_eq!(deque.pop().unwrap(), i);
+            assert_eq!(deque.size, i as usize);
+        }
+        assert_eq!(deque.size, 0);
+        assert_eq!(deque.pop(), None);
+        assert_eq!(deque.size, 0);
+    }
+
+    #[test]
+    fn test_back() {
+        let mut deque: RawDeque<i32> = RawDeque::new();
+        assert_eq!(deque.size, 0);
+        assert_eq!(deque.pop_back(), None);
+        assert_eq!(deque.size, 0);
+
+        for i in 0..10 {
+            deque.push_back(i);
+            assert_eq!(deque.size, (i + 1) as usize);
+        }
+        for i in (0..10).rev() {
+            assert_eq!(deque.pop_back().unwrap(), i);
+            assert_eq!(deque.size, i as usize);
+        }
+        assert_eq!(deque.size, 0);
+        assert_eq!(deque.pop(), None);
+        assert_eq!(deque.size, 0);
+    }
+
+    #[test]
+    fn test_mixed() {
+        let mut deque: RawDeque<i32> = RawDeque::new();
+        for i in 0..10 {
+            if i % 2 == 0 {
+                deque.push(i);
+            } else {
+                deque.push_back(i);
+            }
+            assert_eq!(deque.size, (i + 1) as usize);
+        }
+        for expected in [8, 6, 4, 2, 0, 1, 3, 5, 7] {
+            assert_eq!(deque.pop().unwrap(), expected);
+        }
+        assert_eq!(deque.pop_back().unwrap(), 9);
+        assert_eq!(deque.size, 0);
+        assert_eq!(deque.pop(), None);
+        assert_eq!(deque.pop_back(), None);
+    }
+
+    #[test]
+    fn test_iterator() {
+        let mut deque: RawDeque<String> = RawDeque::new();
+        for i in 0..10 {
+            deque.push(i.to_string());
+        }
+        for (s, ix) in deque.zip((0..10).rev()) {
+            assert_eq!(ix, s.parse::<i32>().unwrap());
+        }
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut deque: RawDeque<i32> = RawDeque::new();
+        for i in 0..BIG {
+            deque.push(i);
+        }
+    }
+
+    #[test]
+    fn peek_test() {
+        let mut deque: RawDeque<String> = RawDeque::new();
+        assert!(deque.front().is_none());
+        assert!(deque.back_mut().is_none());
+        deque.push("a".to_string());
+        assert_eq!(deque.front().unwrap(), "a");
+        assert_eq!(deque.back().unwrap(), "a");
+        deque.push_back("b".to_string());
+        deque.front_mut().unwrap().push('1');
+        deque.back_mut().unwrap().push('2');
+        assert_eq!(deque.front().unwrap(), "a1");
+        assert_eq!(deque.back().unwrap(), "b2");
+        assert_eq!(deque.pop(), Some("a1".to_string()));
+        assert_eq!(deque.front(), deque.back());
+    }
+
+    #[test]
+    fn borrow_iter_test() {
+        let mut deque: RawDeque<i32> = RawDeque::new();
+        assert!(deque.iter().next().is_none());
+        assert!(deque.iter().next_back().is_none());
+        for i in 0..10 {
+            deque.push_back(i);
+        }
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(deque.iter().rev().copied().collect::<Vec<_>>(), (0..10).rev().collect::<Vec<_>>());
+        let mut iter = deque.iter();
+        let mut seen = Vec::new();
+        while let Some(front) = iter.next() {
+            seen.push(*front);
+            if let Some(back) = iter.next_back() {
+                seen.push(*back);
+            }
+        }
+        assert_eq!(seen, vec![0, 9, 1, 8, 2, 7, 3, 6, 4, 5]);
+        assert_eq!((&deque).into_iter().count(), 10);
+        assert_eq!(deque.len(), 10);
+    }
+
+    #[test]
+    fn borrow_iter_mut_test() {
+        let mut deque: RawDeque<i32> = RawDeque::new();
+        for i in 0..9 {
+            deque.push_back(i);
+        }
+        // Hold all the references at once.
+        let mut refs = deque.iter_mut().collect::<Vec<_>>();
+        for elem in refs.iter_mut() {
+            **elem *= 10;
+        }
+        let mut iter = deque.iter_mut();
+        while let (Some(front), Some(back)) = (iter.next(), iter.next_back()) {
+            *front += 1;
+            *back += 2;
+        }
+        assert_eq!(deque.collect::<Vec<_>>(), vec![1, 11, 21, 31, 40, 52, 62, 72, 82]);
+    }
+
+    #[test]
+    fn ownership_test() {
+        use std::rc::Rc;
+        // Every element is dropped exactly once, whether popped or dropped with the deque.
+        let counter = Rc::new(());
+        let mut deque = RawDeque::new();
+        for _ in 0..10 {
+            deque.push(counter.clone());
+            deque.push_back(counter.clone());
+        }
+        assert_eq!(Rc::strong_count(&counter), 21);
+        deque.pop();
+        deque.pop_back();
+        assert_eq!(Rc::strong_count(&counter), 19);
+        drop(deque);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn send_test() {
+        let mut deque = RawDeque::new();
+        for i in 0..100 {
+            deque.push_back(i.to_string());
+        }
+        let handle = std::thread::spawn(move || deque.iter().map(|s| s.len()).sum::<usize>());
+        assert_eq!(handle.join().unwrap(), 190);
+    }
+}
@@ -0,0 +1,308 @@
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use crate::double_ended::DoubleEnded;
+
+/// Deque in a ring buffer: the elements are stored in a `Vec`, starting at index `head` and wrapping
+/// around its end. When the buffer is full, it is replaced with one twice the size, so pushes are
+/// amortized O(1).
+///
+/// ```
+/// use rust_dust_deque::ring::RingDeque;
+///
+/// let mut deque = RingDeque::with_capacity(2);
+/// deque.push_back(2);
+/// deque.push(1);
+/// deque.push_back(3);  // Grows.
+/// assert_eq!(deque.capacity(), 4);
+/// assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+/// assert_eq!(deque.pop_back(), Some(3));
+/// assert_eq!(deque.pop(), Some(1));
+/// ```
+pub struct RingDeque<E> {
+    // Slots `head`, `head + 1`, ... `head + size - 1`, modulo the capacity, are `Some`,
+    // the rest are `None`.
+    buf: Vec<Option<E>>,
+    head: usize,
+    size: usize,
+}
+
+impl<E> RingDeque<E> {
+    /// Capacity of the first buffer, unless given to `with_capacity()`.
+    pub const INITIAL_CAPACITY: usize = 4;
+
+    /// Create an empty deque. Does not allocate.
+    pub fn new() -> Self {
+        RingDeque { buf: Vec::new(), head: 0, size: 0 }
+    }
+
+    /// Create an empty deque with room for `capacity` elements.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut buf = Vec::with_capacity(capacity);
+        buf.resize_with(capacity, || None);
+        RingDeque { buf, head: 0, size: 0 }
+    }
+
+    /// Number of elements the deque can hold before it has to grow.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Insert at the head of the deque.
+    pub fn push(&mut self, elem: E) {
+        if self.size == self.capacity() {
+            self.grow();
+        }
+        self.head = (self.head + self.capacity() - 1) % self.capacity();
+        self.buf[self.head] = Some(elem);
+        self.size += 1;
+    }
+
+    /// Pop off the head of the deque.
+    pub fn pop(&mut self) -> Option<E> {
+        if self.size == 0 {
+            return None;
+        }
+        let elem = self.buf[self.head].take();
+        self.head = (self.head + 1) % self.capacity();
+        self.size -= 1;
+        elem
+    }
+
+    /// Insert at the back of the deque.
+    pub fn push_back(&mut self, elem: E) {
+        if self.size == self.capacity() {
+            self.grow();
+        }
+        let tail = self.slot(self.size);
+        self.buf[tail] = Some(elem);
+        self.size += 1;
+    }
+
+    /// Pop off the back of the deque.
+    pub fn pop_back(&mut self) -> Option<E> {
+        if self.size == 0 {
+            return None;
+        }
+        self.size -= 1;
+        let tail = self.slot(self.size);
+        self.buf[tail].take()
+    }
+
+    /// Number of elements in the deque.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the deque has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The head element, if any, without removing it.
+    pub fn front(&self) -> Option<&E> {
+        self.get(0)
+    }
+
+    /// The tail element, if any, without removing it.
+    pub fn back(&self) -> Option<&E> {
+        self.size.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    /// Mutable head element, if any, without removing it.
+    pub fn front_mut(&mut self) -> Option<&mut E> {
+        self.get_mut(0)
+    }
+
+    /// Mutable tail element, if any, without removing it.
+    pub fn back_mut(&mut self) -> Option<&mut E> {
+        self.size.checked_sub(1).and_then(|i| self.get_mut(i))
+    }
+
+    /// The element at `index` from the head, if there is one. O(1), unlike in a linked deque.
+    pub fn get(&self, index: usize) -> Option<&E> {
+        if index < self.size {
+            self.buf[self.slot(index)].as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Mutable element at `index` from the head, if there is one.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut E> {
+        if index < self.size {
+            let slot = self.slot(index);
+            self.buf[slot].as_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over the elements, head to tail or, with `rev()`, tail to head, without consuming them.
+    pub fn iter(&self) -> Iter<'_, E> {
+        Iter { deque: self, front: 0, back: self.size }
+    }
+
+    /// Index in `buf` of the element at `index` from the head.
+    fn slot(&self, index: usize) -> usize {
+        (self.head + index) % self.capacity()
+    }
+
+    /// Move the elements to a buffer twice the size, unwrapping them to start at index 0.
+    fn grow(&mut self) {
+        let new_capacity = match self.capacity() {
+            0 => Self::INITIAL_CAPACITY,
+            capacity => capacity * 2,
+        };
+        let mut new_buf = Vec::with_capacity(new_capacity);
+        for i in 0..self.size {
+            let slot = self.slot(i);
+            new_buf.push(self.buf[slot].take());
+        }
+        new_buf.resize_with(new_capacity, || None);
+        self.buf = new_buf;
+        self.head = 0;
+    }
+}
+
+impl<E> Default for RingDeque<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Debug> Debug for RingDeque<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<E> DoubleEnded for RingDeque<E> {
+    type Elem = E;
+
+    fn push(&mut self, elem: E) {
+        RingDeque::push(self, elem)
+    }
+
+    fn pop(&mut self) -> Option<E> {
+        RingDeque::pop(self)
+    }
+
+    fn push_back(&mut self, elem: E) {
+        RingDeque::push_back(self, elem)
+    }
+
+    fn pop_back(&mut self) -> Option<E> {
+        RingDeque::pop_back(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+/// Borrowing iterator, returned by `RingDeque::iter()`.
+pub struct Iter<'a, E> {
+    deque: &'a RingDeque<E>,
+    // Indices from the head of the next elements to return from either end, `back` exclusive.
+    front: usize,
+    back: usize,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.deque.get(self.front - 1)
+    }
+}
+
+impl<E> DoubleEndedIterator for Iter<'_, E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.deque.get(self.back)
+    }
+}
+
+impl<'a, E> IntoIterator for &'a RingDeque<E> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The `DoubleEnded` suite covers the basics. These tests are about the ring buffer.
+#[cfg(test)]
+mod tests {
+    use crate::ring::RingDeque;
+
+    #[test]
+    fn grow_test() {
+        let mut deque: RingDeque<String> = RingDeque::new();
+        assert_eq!(deque.capacity(), 0);
+        deque.push("0".to_string());
+        assert_eq!(deque.capacity(), RingDeque::<String>::INITIAL_CAPACITY);
+        // Make the elements wrap around the end of the buffer before it grows.
+        for i in 1..4 {
+            deque.push(i.to_string());
+        }
+        assert_eq!(deque.capacity(), 4);
+        for i in 4..9 {
+            deque.push_back(i.to_string());
+        }
+        assert_eq!(deque.capacity(), 16);
+        assert_eq!(format!("{:?}", deque), r#"["3", "2", "1", "0", "4", "5", "6", "7", "8"]"#);
+        let mut deque: RingDeque<i32> = RingDeque::with_capacity(0);
+        deque.push_back(1);
+        assert_eq!(deque.capacity(), 4);
+    }
+
+    #[test]
+    fn peek_test() {
+        let mut deque: RingDeque<i32> = RingDeque::with_capacity(3);
+        assert_eq!(deque.front(), None);
+        assert_eq!(deque.back_mut(), None);
+        for i in 0..3 {
+            deque.push(i);
+        }
+        // [2, 1, 0], with the head at the end of the buffer.
+        assert_eq!(deque.front(), Some(&2));
+        assert_eq!(deque.back(), Some(&0));
+        *deque.front_mut().unwrap() = 20;
+        *deque.back_mut().unwrap() = 10;
+        assert_eq!(deque.get(0), Some(&20));
+        assert_eq!(deque.get(1), Some(&1));
+        assert_eq!(deque.get(2), Some(&10));
+        assert_eq!(deque.get(3), None);
+        *deque.get_mut(1).unwrap() = 15;
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![20, 15, 10]);
+    }
+
+    #[test]
+    fn iter_test() {
+        let mut deque: RingDeque<i32> = RingDeque::with_capacity(8);
+        for i in 0..6 {
+            deque.push_back(i);
+        }
+        for i in 0..4 {
+            assert_eq!(deque.pop(), Some(i));
+        }
+        for i in 6..10 {
+            deque.push_back(i);
+        }
+        assert_eq!(deque.capacity(), 8);
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), (4..10).collect::<Vec<_>>());
+        assert_eq!(deque.iter().rev().copied().collect::<Vec<_>>(), (4..10).rev().collect::<Vec<_>>());
+        let mut iter = deque.iter();
+        assert_eq!(iter.next(), Some(&4));
+        assert_eq!(iter.next_back(), Some(&9));
+        assert_eq!(iter.count(), 4);
+        assert_eq!((&deque).into_iter().count(), 6);
+    }
+}
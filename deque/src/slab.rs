@@ -0,0 +1,405 @@
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use crate::double_ended::DoubleEnded;
+
+/// Names an element of a `SlabList`, for as long as the element is in the list. Once it is removed,
+/// the handle is _stale_: methods given it find nothing, even after its slot is reused for a new
+/// element, because the slot's generation has moved on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u64,
+}
+
+/// Error returned when a handle is stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleHandle {
+    pub handle: Handle,
+}
+
+struct Slot<E> {
+    // Incremented whenever the slot is freed, which makes the handles to its old element stale.
+    generation: u64,
+    // `Some` if the slot is in use.
+    elem: Option<E>,
+    prev: Option<usize>,
+    // For a free slot, the next free slot.
+    next: Option<usize>,
+}
+
+/// Doubly-linked list whose nodes are slots in a `Vec`, linked by their indices, instead of
+/// separately allocated nodes linked by pointers. Freed slots are kept on a free list and reused.
+///
+/// Pushes return a `Handle`, with which the element can be reached, removed, or moved to either
+/// end in O(1). A handle holds the slot's index and generation, so unlike a pointer, a stale one
+/// is detected rather than followed. The links between the nodes need no generations: the list
+/// keeps them up to date itself.
+///
+/// No `Rc`s means no reference cycles, and no need for `RefCell`: `get()` returns a plain
+/// reference, and dropping the list just drops the `Vec`.
+///
+/// ```
+/// use rust_dust_deque::slab::SlabList;
+///
+/// let mut list = SlabList::new();
+/// let a = list.push_back("a");
+/// let b = list.push_back("b");
+/// list.push_back("c");
+/// list.move_to_front(b).unwrap();
+/// assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec!["b", "a", "c"]);
+/// assert_eq!(list.remove(a), Some("a"));
+/// assert_eq!(list.remove(a), None);  // Stale.
+/// assert_eq!(list.get(b), Some(&"b"));
+/// ```
+pub struct SlabList<E> {
+    slots: Vec<Slot<E>>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    // Head of the free list.
+    free: Option<usize>,
+    size: usize,
+}
+
+impl<E> SlabList<E> {
+    /// Create an empty list. Does not allocate.
+    pub fn new() -> Self {
+        SlabList { slots: Vec::new(), head: None, tail: None, free: None, size: 0 }
+    }
+
+    /// Create an empty list with room for `capacity` elements.
+    pub fn with_capacity(capacity: usize) -> Self {
+        SlabList { slots: Vec::with_capacity(capacity), head: None, tail: None, free: None, size: 0 }
+    }
+
+    /// Insert at the head of the list, and return the new element's handle.
+    pub fn push(&mut self, elem: E) -> Handle {
+        let index = self.alloc(elem);
+        self.link_front(index);
+        self.handle(index)
+    }
+
+    /// Pop off the head of the list.
+    pub fn pop(&mut self) -> Option<E> {
+        let index = self.head?;
+        self.unlink(index);
+        Some(self.release(index))
+    }
+
+    /// Insert at the back of the list, and return the new element's handle.
+    pub fn push_back(&mut self, elem: E) -> Handle {
+        let index = self.alloc(elem);
+        self.link_back(index);
+        self.handle(index)
+    }
+
+    /// Pop off the back of the list.
+    pub fn pop_back(&mut self) -> Option<E> {
+        let index = self.tail?;
+        self.unlink(index);
+        Some(self.release(index))
+    }
+
+    /// Number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the list has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The head element, if any, without removing it.
+    pub fn front(&self) -> Option<&E> {
+        self.slots[self.head?].elem.as_ref()
+    }
+
+    /// The tail element, if any, without removing it.
+    pub fn back(&self) -> Option<&E> {
+        self.slots[self.tail?].elem.as_ref()
+    }
+
+    /// Handle of the head element, if any.
+    pub fn front_handle(&self) -> Option<Handle> {
+        self.head.map(|index| self.handle(index))
+    }
+
+    /// Handle of the tail element, if any.
+    pub fn back_handle(&self) -> Option<Handle> {
+        self.tail.map(|index| self.handle(index))
+    }
+
+    /// `true` if `handle` is not stale.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.index(handle).is_some()
+    }
+
+    /// The element named by `handle`, or `None` if the handle is stale.
+    pub fn get(&self, handle: Handle) -> Option<&E> {
+        self.slots[self.index(handle)?].elem.as_ref()
+    }
+
+    /// Mutable element named by `handle`, or `None` if the handle is stale.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut E> {
+        let index = self.index(handle)?;
+        self.slots[index].elem.as_mut()
+    }
+
+    /// Handle of the element after the one named by `handle`. `None` at the tail, or if the
+    /// handle is stale.
+    pub fn next(&self, handle: Handle) -> Option<Handle> {
+        self.slots[self.index(handle)?].next.map(|index| self.handle(index))
+    }
+
+    /// Handle of the element before the one named by `handle`. `None` at the head, or if the
+    /// handle is stale.
+    pub fn prev(&self, handle: Handle) -> Option<Handle> {
+        self.slots[self.index(handle)?].prev.map(|index| self.handle(index))
+    }
+
+    /// Remove the element named by `handle` and return it, or `None` if the handle is stale. O(1).
+    pub fn remove(&mut self, handle: Handle) -> Option<E> {
+        let index = self.index(handle)?;
+        self.unlink(index);
+        Some(self.release(index))
+    }
+
+    /// Move the element named by `handle` to the head of the list. O(1). The handle stays valid.
+    pub fn move_to_front(&mut self, handle: Handle) -> Result<(), StaleHandle> {
+        let index = self.index(handle).ok_or(StaleHandle { handle })?;
+        self.unlink(index);
+        self.link_front(index);
+        Ok(())
+    }
+
+    /// Move the element named by `handle` to the back of the list. O(1). The handle stays valid.
+    pub fn move_to_back(&mut self, handle: Handle) -> Result<(), StaleHandle> {
+        let index = self.index(handle).ok_or(StaleHandle { handle })?;
+        self.unlink(index);
+        self.link_back(index);
+        Ok(())
+    }
+
+    /// Drop all elements, making all handles stale. Keeps the slots, for reuse.
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Iterate over the elements, head to tail or, with `rev()`, tail to head, without consuming them.
+    pub fn iter(&self) -> Iter<'_, E> {
+        Iter { list: self, front: self.head, back: self.tail, remaining: self.size }
+    }
+
+    /// Index of the slot named by `handle`, if it is not stale.
+    fn index(&self, handle: Handle) -> Option<usize> {
+        let slot = self.slots.get(handle.index)?;
+        (slot.generation == handle.generation && slot.elem.is_some()).then_some(handle.index)
+    }
+
+    fn handle(&self, index: usize) -> Handle {
+        Handle { index, generation: self.slots[index].generation }
+    }
+
+    /// Put `elem` in a free slot, or a new one, without linking it.
+    fn alloc(&mut self, elem: E) -> usize {
+        match self.free {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                self.free = slot.next;
+                slot.elem = Some(elem);
+                index
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, elem: Some(elem), prev: None, next: None });
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    /// Take the element out of an unlinked slot, and put the slot on the free list.
+    fn release(&mut self, index: usize) -> E {
+        let slot = &mut self.slots[index];
+        slot.generation += 1;
+        slot.prev = None;
+        slot.next = self.free.replace(index);
+        slot.elem.take().unwrap()
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let Slot { prev, next, .. } = self.slots[index];
+        match prev {
+            None => self.head = next,
+            Some(prev) => self.slots[prev].next = next,
+        }
+        match next {
+            None => self.tail = prev,
+            Some(next) => self.slots[next].prev = prev,
+        }
+        self.size -= 1;
+    }
+
+    fn link_front(&mut self, index: usize) {
+        self.slots[index].prev = None;
+        self.slots[index].next = self.head;
+        match self.head {
+            None => self.tail = Some(index),
+            Some(old_head) => self.slots[old_head].prev = Some(index),
+        }
+        self.head = Some(index);
+        self.size += 1;
+    }
+
+    fn link_back(&mut self, index: usize) {
+        self.slots[index].next = None;
+        self.slots[index].prev = self.tail;
+        match self.tail {
+            None => self.head = Some(index),
+            Some(old_tail) => self.slots[old_tail].next = Some(index),
+        }
+        self.tail = Some(index);
+        self.size += 1;
+    }
+}
+
+impl<E> Default for SlabList<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Debug> Debug for SlabList<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<E> DoubleEnded for SlabList<E> {
+    type Elem = E;
+
+    fn push(&mut self, elem: E) {
+        SlabList::push(self, elem);
+    }
+
+    fn pop(&mut self) -> Option<E> {
+        SlabList::pop(self)
+    }
+
+    fn push_back(&mut self, elem: E) {
+        SlabList::push_back(self, elem);
+    }
+
+    fn pop_back(&mut self) -> Option<E> {
+        SlabList::pop_back(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+/// Borrowing iterator, returned by `SlabList::iter()`.
+pub struct Iter<'a, E> {
+    list: &'a SlabList<E>,
+    // Slots of the next elements to return from either end.
+    front: Option<usize>,
+    back: Option<usize>,
+    // Keeps the ends from crossing.
+    remaining: usize,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let slot = &self.list.slots[self.front?];
+        self.front = slot.next;
+        slot.elem.as_ref()
+    }
+}
+
+impl<E> DoubleEndedIterator for Iter<'_, E> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let slot = &self.list.slots[self.back?];
+        self.back = slot.prev;
+        slot.elem.as_ref()
+    }
+}
+
+impl<'a, E> IntoIterator for &'a SlabList<E> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The `DoubleEnded` suite covers the basics. These tests are about the handles.
+#[cfg(test)]
+mod tests {
+    use crate::slab::{SlabList, StaleHandle};
+
+    #[test]
+    fn handle_test() {
+        let mut list = SlabList::new();
+        let handles = (0..5).map(|i| list.push_back(i)).collect::<Vec<_>>();
+        assert_eq!(list.get(handles[2]), Some(&2));
+        *list.get_mut(handles[2]).unwrap() = 20;
+        assert_eq!(list.remove(handles[2]), Some(20));
+        assert_eq!(list.remove(handles[0]), Some(0));
+        assert_eq!(list.remove(handles[4]), Some(4));
+        assert_eq!(format!("{:?}", list), "[1, 3]");
+        assert_eq!(list.front_handle(), Some(handles[1]));
+        assert_eq!(list.back_handle(), Some(handles[3]));
+        assert_eq!(list.next(handles[1]), Some(handles[3]));
+        assert_eq!(list.prev(handles[1]), None);
+        assert_eq!(list.next(handles[2]), None);
+        // Stale handles find nothing, even once their slots are reused.
+        let reused = list.push(5);
+        assert!(!list.contains(handles[4]));
+        assert!(list.contains(reused));
+        assert_eq!(list.get(handles[4]), None);
+        assert_eq!(list.get_mut(handles[2]), None);
+        assert_eq!(list.move_to_back(handles[0]), Err(StaleHandle { handle: handles[0] }));
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![5, 1, 3]);
+        // No new slots until the freed ones are used up.
+        list.push(6);
+        list.push(7);
+        assert_eq!(list.slots.len(), 5);
+        list.push(8);
+        assert_eq!(list.slots.len(), 6);
+        list.clear();
+        assert!(list.is_empty());
+        assert!(!list.contains(reused));
+    }
+
+    #[test]
+    fn move_test() {
+        let mut list = SlabList::new();
+        let handles = (0..4).map(|i| list.push_back(i)).collect::<Vec<_>>();
+        list.move_to_front(handles[2]).unwrap();
+        list.move_to_back(handles[0]).unwrap();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![2, 1, 3, 0]);
+        assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), vec![0, 3, 1, 2]);
+        // Moving an element to where it already is.
+        list.move_to_front(handles[2]).unwrap();
+        list.move_to_back(handles[0]).unwrap();
+        assert_eq!(list.front(), Some(&2));
+        assert_eq!(list.back(), Some(&0));
+        assert_eq!(list.len(), 4);
+        for handle in &handles {
+            assert!(list.contains(*handle));
+        }
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&0));
+        assert_eq!(iter.count(), 2);
+        assert_eq!((&list).into_iter().count(), 4);
+    }
+}
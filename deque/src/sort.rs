@@ -0,0 +1,282 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use crate::deque::{Deque, DequeNode};
+
+type Link<E> = Option<Rc<RefCell<DequeNode<E>>>>;
+
+impl<E> Deque<E> {
+    /// Sort the elements in ascending order. Stable: equal elements keep their relative order.
+    /// O(n log n), and neither moves elements nor allocates: see `sort_by()`.
+    /// ```
+    /// use rust_dust_deque::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// for i in [3, 1, 2] {
+    ///     deque.push_back(i);
+    /// }
+    /// deque.sort();
+    /// assert_eq!(deque.collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// ```
+    pub fn sort(&mut self) where E: Ord {
+        self.sort_by(E::cmp);
+    }
+
+    /// Sort the elements by the keys `f()` extracts from them. Stable. Calls `f()` twice per comparison.
+    pub fn sort_by_key<K: Ord, F: FnMut(&E) -> K>(&mut self, mut f: F) {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Sort the elements with a comparator. Stable.
+    ///
+    /// This is a bottom-up merge sort that only relinks the nodes. Single nodes are fed, in order,
+    /// into an array of bins, where bin `i` is either empty or holds a sorted run of `2^i` nodes.
+    /// A new node is merged with the run in bin 0, the result with the run in bin 1, and so on,
+    /// until an empty bin is found, much like adding 1 to a binary number. In the end, all bins are
+    /// merged together. The `prev` links are ignored until then, and fixed in one last pass.
+    ///
+    /// If `cmp` panics, every node is still on one of the chains the sort works with, and these are
+    /// linked back into the deque, in no particular order, before unwinding.
+    pub fn sort_by<F: FnMut(&E, &E) -> Ordering>(&mut self, mut cmp: F) {
+        self.tail = None;
+        let rest = self.head.take();
+        let mut sort = SortGuard {
+            deque: self,
+            // 64 bins can hold `2^64 - 1` nodes.
+            bins: core::array::from_fn(|_| None),
+            rest,
+            left: None,
+            right: None,
+            merged: None,
+            merged_tail: None,
+        };
+        while let Some(node) = sort.rest.take() {
+            sort.rest = node.borrow_mut().next.take();
+            // Break the `prev` link, rather than leave it pointing at the wrong node.
+            node.borrow_mut().prev = None;
+            let mut carry = Some(node);
+            for i in 0..sort.bins.len() {
+                match sort.bins[i].take() {
+                    None => {
+                        sort.bins[i] = carry;
+                        break;
+                    }
+                    // Bins hold runs of elements that came earlier, so they go on the left.
+                    Some(run) => carry = sort.merge(Some(run), carry, &mut cmp),
+                }
+            }
+        }
+        let mut sorted = None;
+        for i in 0..sort.bins.len() {
+            if sort.bins[i].is_some() {
+                let run = sort.bins[i].take();
+                sorted = sort.merge(run, sorted, &mut cmp);
+            }
+        }
+        // Dropping the guard restores the `prev` links, the tail and the size.
+        sort.rest = sorted;
+    }
+
+    /// Insert an element after all elements that are less than or equal to it, so that a sorted
+    /// deque stays sorted. O(n).
+    pub fn insert_sorted(&mut self, elem: E) where E: Ord {
+        let mut cursor = self.cursor_front_mut();
+        while cursor.current().is_some_and(|curr| *curr <= elem) {
+            cursor.move_next();
+        }
+        // At the ghost position, this inserts at the tail.
+        cursor.insert_before(elem);
+    }
+}
+
+/// State of `Deque::sort_by()`. Whenever `cmp` is called, each node is on exactly one of the chains
+/// of `next` links held here, so that if it panics, `drop()` can link them all back into the deque.
+struct SortGuard<'a, E> {
+    deque: &'a mut Deque<E>,
+    bins: [Link<E>; 64],
+    // Nodes not fed into the bins yet.
+    rest: Link<E>,
+    // The two chains being merged, and the result so far.
+    left: Link<E>,
+    right: Link<E>,
+    merged: Link<E>,
+    merged_tail: Link<E>,
+}
+
+impl<E> SortGuard<'_, E> {
+    /// Merge two sorted chains of `next` links. On ties, `left` comes first, which makes the sort stable.
+    fn merge<F: FnMut(&E, &E) -> Ordering>(&mut self, left: Link<E>, right: Link<E>, cmp: &mut F) -> Link<E> {
+        self.left = left;
+        self.right = right;
+        loop {
+            let take_right = match (&self.left, &self.right) {
+                (Some(l), Some(r)) => cmp(&r.borrow().elem, &l.borrow().elem) == Ordering::Less,
+                // One chain is used up: the rest of the other one is already linked.
+                _ => {
+                    let rest = self.left.take().or_else(|| self.right.take());
+                    self.append(rest);
+                    break;
+                }
+            };
+            let from = if take_right { &mut self.right } else { &mut self.left };
+            let node = from.take().unwrap();
+            *from = node.borrow_mut().next.take();
+            self.append(Some(node));
+        }
+        self.merged_tail = None;
+        self.merged.take()
+    }
+
+    /// Append a chain to `merged`. Only a single node moves `merged_tail`, as a longer chain
+    /// is only appended to finish a merge.
+    fn append(&mut self, chain: Link<E>) {
+        let Some(node) = chain else { return };
+        match &self.merged_tail {
+            None => self.merged = Some(node.clone()),
+            Some(tail) => tail.borrow_mut().next = Some(node.clone()),
+        }
+        self.merged_tail = Some(node);
+    }
+}
+
+impl<E> Drop for SortGuard<'_, E> {
+    /// Link all chains into the deque, fixing the `prev` links, the tail and the size on the way.
+    /// After a successful sort, only `rest` is left, holding all nodes in order.
+    fn drop(&mut self) {
+        self.merged_tail = None;
+        let chains = [self.merged.take(), self.left.take(), self.right.take()].into_iter()
+            .chain(self.bins.iter_mut().map(Option::take))
+            .chain([self.rest.take()]);
+        let mut head: Link<E> = None;
+        let mut tail: Link<E> = None;
+        let mut size = 0;
+        for chain in chains {
+            let mut curr = chain;
+            while let Some(node) = curr {
+                curr = node.borrow_mut().next.take();
+                node.borrow_mut().prev = tail.clone();
+                match &tail {
+                    None => head = Some(node.clone()),
+                    Some(tail) => tail.borrow_mut().next = Some(node.clone()),
+                }
+                tail = Some(node);
+                size += 1;
+            }
+        }
+        self.deque.head = head;
+        self.deque.tail = tail;
+        self.deque.size = size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deque::Deque;
+
+    /// Deterministic pseudo-random numbers, from a linear congruential generator.
+    fn random(count: usize, modulus: u64) -> Vec<u64> {
+        let mut state: u64 = 42;
+        (0..count).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % modulus
+        }).collect()
+    }
+
+    fn deque_of<E: Clone>(elems: &[E]) -> Deque<E> {
+        let mut deque = Deque::new();
+        for elem in elems {
+            deque.push_back(elem.clone());
+        }
+        deque
+    }
+
+    /// Check both directions, so that broken `prev` links don't go unnoticed.
+    fn contents<E: Clone + PartialEq + core::fmt::Debug>(deque: &Deque<E>) -> Vec<E> {
+        let forward = deque.iter().map(|elem| elem.clone()).collect::<Vec<_>>();
+        let mut backward = deque.iter().rev().map(|elem| elem.clone()).collect::<Vec<_>>();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward.len(), deque.len());
+        forward
+    }
+
+    #[test]
+    fn sort_test() {
+        for count in [0, 1, 2, 3, 7, 8, 9, 100, 1000] {
+            let elems = random(count, 50);
+            let mut deque = deque_of(&elems);
+            deque.sort();
+            let mut expected = elems.clone();
+            expected.sort();
+            assert_eq!(contents(&deque), expected);
+            assert_eq!(deque.back().map(|elem| *elem), expected.last().copied());
+            deque.sort_by(|a, b| b.cmp(a));
+            expected.reverse();
+            assert_eq!(contents(&deque), expected);
+        }
+    }
+
+    #[test]
+    fn stable_test() {
+        // Sort by the key only: equal keys must keep their order, given by the second field.
+        let elems = random(500, 10).into_iter().enumerate().map(|(i, key)| (key, i)).collect::<Vec<_>>();
+        let mut deque = deque_of(&elems);
+        deque.sort_by_key(|(key, _)| *key);
+        let mut expected = elems.clone();
+        expected.sort_by_key(|(key, _)| *key);
+        assert_eq!(contents(&deque), expected);
+    }
+
+    #[test]
+    fn insert_sorted_test() {
+        let mut deque = Deque::new();
+        for key in random(200, 20) {
+            deque.insert_sorted(key);
+        }
+        let elems = contents(&deque);
+        assert!(elems.windows(2).all(|pair| pair[0] <= pair[1]));
+        // After equal elements.
+        let mut deque = deque_of(&[(1, 'a'), (2, 'a'), (3, 'a')]);
+        deque.insert_sorted((2, 'b'));
+        deque.insert_sorted((0, 'b'));
+        deque.insert_sorted((4, 'b'));
+        assert_eq!(contents(&deque), vec![(0, 'b'), (1, 'a'), (2, 'a'), (2, 'b'), (3, 'a'), (4, 'b')]);
+    }
+
+    #[test]
+    fn big_sort_test() {
+        let mut deque = deque_of(&random(100000, u64::MAX));
+        deque.sort();
+        let elems = contents(&deque);
+        assert!(elems.windows(2).all(|pair| pair[0] <= pair[1]));
+        // Sorting sorted input.
+        deque.sort();
+        assert_eq!(deque.len(), 100000);
+    }
+
+    #[test]
+    fn panic_test() {
+        use std::panic::{self, AssertUnwindSafe};
+        let elems = random(1000, 100);
+        for limit in [0, 1, 10, 500, 5000] {
+            let mut deque = deque_of(&elems);
+            let mut calls = 0;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                deque.sort_by(|a, b| {
+                    calls += 1;
+                    assert!(calls <= limit, "comparator panicked");
+                    a.cmp(b)
+                });
+            }));
+            assert!(result.is_err());
+            // All elements are still there, and the links and the size agree.
+            let mut remaining = contents(&deque);
+            remaining.sort();
+            let mut expected = elems.clone();
+            expected.sort();
+            assert_eq!(remaining, expected);
+            deque.sort();
+            assert_eq!(contents(&deque), expected);
+        }
+    }
+}
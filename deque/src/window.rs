@@ -0,0 +1,256 @@
+use core::cell::Ref;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+use crate::deque::Deque;
+
+/// Which end of the order `SlidingWindow` tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extremum {
+    Min,
+    Max,
+}
+
+/// How far back a window reaches.
+enum Span {
+    // The last `n` values.
+    Count(u64),
+    // The values pushed less than `length` nanoseconds ago, stamped with their nanoseconds since
+    // `origin`, the first instant the window was given.
+    #[cfg(feature = "std")]
+    Time { length: u64, origin: Option<Instant> },
+}
+
+/// Minimum or maximum of the last `n` values pushed, or, with `std`, of the values pushed within
+/// a duration, updated in amortized O(1) per push.
+///
+/// The values are kept in a _monotonic deque_: a `Deque` of the values that could still become
+/// the extremum, with their stamps, oldest at the head. For a maximum, a value is of no further
+/// use once a newer value is at least as large, since the newer one stays in the window longer.
+/// So `push()` first pops all such values off the back, which leaves the deque decreasing from
+/// head to tail, and the maximum at the head. Old values leave from the head as the window moves
+/// on. Every value is pushed and popped once, hence the amortized O(1).
+///
+/// ```
+/// use rust_dust_deque::window::{Extremum, SlidingWindow};
+///
+/// let mut window = SlidingWindow::new(3, Extremum::Max);
+/// let mut maxima = Vec::new();
+/// for value in [1, 3, 2, 1, 0, 4] {
+///     window.push(value);
+///     maxima.push(*window.get().unwrap());
+/// }
+/// assert_eq!(maxima, vec![1, 3, 3, 3, 2, 4]);
+/// ```
+pub struct SlidingWindow<T> {
+    // Stamps and values, oldest at the head, and never beaten by a newer value behind them.
+    deque: Deque<(u64, T)>,
+    extremum: Extremum,
+    span: Span,
+    // Number of values pushed so far, which stamps them in a count window.
+    pushed: u64,
+}
+
+impl<T: Ord> SlidingWindow<T> {
+    /// Create an empty window over the last `size` values. Panics if `size` is 0.
+    pub fn new(size: usize, extremum: Extremum) -> Self {
+        assert!(size > 0, "size must be positive");
+        SlidingWindow { deque: Deque::new(), extremum, span: Span::Count(size as u64), pushed: 0 }
+    }
+
+    /// Create an empty window over the values pushed less than `length` ago.
+    /// Panics if `length` is 0.
+    #[cfg(feature = "std")]
+    pub fn with_duration(length: Duration, extremum: Extremum) -> Self {
+        assert!(!length.is_zero(), "length must be positive");
+        let length = u64::try_from(length.as_nanos()).unwrap_or(u64::MAX);
+        SlidingWindow { deque: Deque::new(), extremum, span: Span::Time { length, origin: None }, pushed: 0 }
+    }
+
+    /// Add a value to the window, as of now.
+    pub fn push(&mut self, value: T) {
+        let stamp = match self.span {
+            Span::Count(_) => self.pushed,
+            #[cfg(feature = "std")]
+            Span::Time { .. } => self.time_stamp(Instant::now()),
+        };
+        self.push_stamped(stamp, value);
+    }
+
+    /// Add a value to the window, as of `at`, which must not be earlier than the previous push
+    /// or read. In a count window, the same as `push()`.
+    #[cfg(feature = "std")]
+    pub fn push_at(&mut self, value: T, at: Instant) {
+        let stamp = match self.span {
+            Span::Count(_) => self.pushed,
+            Span::Time { .. } => self.time_stamp(at),
+        };
+        self.push_stamped(stamp, value);
+    }
+
+    /// The minimum or maximum of the values in the window, as of now, or `None` if there are none.
+    pub fn get(&mut self) -> Option<Ref<'_, T>> {
+        #[cfg(feature = "std")]
+        if let Span::Time { .. } = self.span {
+            let now = self.time_stamp(Instant::now());
+            self.expire(now);
+        }
+        self.front()
+    }
+
+    /// The minimum or maximum of the values in the window, as of `at`, which must not be earlier
+    /// than the previous push or read. In a count window, the same as `get()`.
+    #[cfg(feature = "std")]
+    pub fn get_at(&mut self, at: Instant) -> Option<Ref<'_, T>> {
+        if let Span::Time { .. } = self.span {
+            let now = self.time_stamp(at);
+            self.expire(now);
+        }
+        self.front()
+    }
+
+    /// Number of values that could still become the extremum: at most the window's size, and
+    /// usually far fewer. Mostly of interest for testing.
+    pub fn candidates(&self) -> usize {
+        self.deque.len()
+    }
+
+    /// Empty the window.
+    pub fn clear(&mut self) {
+        self.deque = Deque::new();
+    }
+
+    fn push_stamped(&mut self, stamp: u64, value: T) {
+        while self.deque.back().is_some_and(|back| !self.beats(&back.1, &value)) {
+            self.deque.pop_back();
+        }
+        self.deque.push_back((stamp, value));
+        self.pushed += 1;
+        self.expire(stamp);
+    }
+
+    /// Pop the values that are out of the window as of `now`, a stamp: those stamped `span` or
+    /// more before it. In a count window, `now` is the stamp of the value pushed last.
+    #[cfg_attr(not(feature = "std"), allow(clippy::infallible_destructuring_match))]
+    fn expire(&mut self, now: u64) {
+        let span = match self.span {
+            Span::Count(n) => n,
+            #[cfg(feature = "std")]
+            Span::Time { length, .. } => length,
+        };
+        while self.deque.front().is_some_and(|front| front.0.saturating_add(span) <= now) {
+            self.deque.pop();
+        }
+    }
+
+    /// `true` if `older` stays the extremum with `newer` in the window.
+    fn beats(&self, older: &T, newer: &T) -> bool {
+        match self.extremum {
+            Extremum::Min => older < newer,
+            Extremum::Max => older > newer,
+        }
+    }
+
+    fn front(&self) -> Option<Ref<'_, T>> {
+        self.deque.front().map(|front| Ref::map(front, |(_, value)| value))
+    }
+
+    /// Stamp for `at` in a time window: nanoseconds since the first instant given.
+    #[cfg(feature = "std")]
+    fn time_stamp(&mut self, at: Instant) -> u64 {
+        let Span::Time { origin, .. } = &mut self.span else {
+            unreachable!("not a time window");
+        };
+        let origin = *origin.get_or_insert(at);
+        u64::try_from(at.saturating_duration_since(origin).as_nanos()).unwrap_or(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use std::time::{Duration, Instant};
+    use crate::window::{Extremum, SlidingWindow};
+
+    /// Deterministic pseudo-random numbers, from a linear congruential generator.
+    fn random(count: usize, modulus: u64) -> Vec<u64> {
+        let mut state: u64 = 7;
+        (0..count).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % modulus
+        }).collect()
+    }
+
+    #[test]
+    fn count_test() {
+        let values = random(1000, 100);
+        for size in [1, 2, 5, 64] {
+            let mut max = SlidingWindow::new(size, Extremum::Max);
+            let mut min = SlidingWindow::new(size, Extremum::Min);
+            for (i, value) in values.iter().enumerate() {
+                max.push(*value);
+                min.push(*value);
+                let window = &values[(i + 1).saturating_sub(size)..=i];
+                assert_eq!(*max.get().unwrap(), *window.iter().max().unwrap());
+                assert_eq!(*min.get().unwrap(), *window.iter().min().unwrap());
+                assert!(max.candidates() <= size);
+            }
+        }
+    }
+
+    #[test]
+    fn monotonic_test() {
+        // Rising values make all older ones useless for a maximum, and all useful for a minimum.
+        let mut max = SlidingWindow::new(10, Extremum::Max);
+        let mut min = SlidingWindow::new(10, Extremum::Min);
+        for i in 0..100 {
+            max.push(i);
+            min.push(i);
+        }
+        assert_eq!(max.candidates(), 1);
+        assert_eq!(min.candidates(), 10);
+        assert_eq!(*min.get().unwrap(), 90);
+        // Ties keep the newest.
+        max.push(99);
+        assert_eq!(max.candidates(), 1);
+        max.clear();
+        assert!(max.get().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn time_test() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut window = SlidingWindow::with_duration(Duration::from_secs(10), Extremum::Max);
+        assert!(window.get_at(start).is_none());
+        window.push_at(5, at(0));
+        window.push_at(3, at(4));
+        window.push_at(4, at(8));
+        assert_eq!(*window.get_at(at(9)).unwrap(), 5);
+        // 5 is exactly 10 seconds old: out.
+        assert_eq!(*window.get_at(at(10)).unwrap(), 4);
+        window.push_at(1, at(17));
+        assert_eq!(*window.get_at(at(17)).unwrap(), 4);
+        assert_eq!(*window.get_at(at(18)).unwrap(), 1);
+        assert!(window.get_at(at(27)).is_none());
+        // Bursts of values at the same instant.
+        for value in [2, 7, 6] {
+            window.push_at(value, at(30));
+        }
+        assert_eq!(*window.get_at(at(39)).unwrap(), 7);
+        // A count window ignores the instants.
+        let mut window = SlidingWindow::new(2, Extremum::Min);
+        window.push_at(1, at(0));
+        window.push_at(2, at(100));
+        assert_eq!(*window.get_at(at(200)).unwrap(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn now_test() {
+        let mut window = SlidingWindow::with_duration(Duration::from_secs(3600), Extremum::Min);
+        window.push(3);
+        window.push(2);
+        assert_eq!(*window.get().unwrap(), 2);
+    }
+}
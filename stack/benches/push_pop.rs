@@ -0,0 +1,73 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use rust_dust_stack::pooled::PooledStack;
+use rust_dust_stack::small::SmallStack;
+use rust_dust_stack::stack::Stack;
+use rust_dust_stack::unrolled::UnrolledStack;
+
+// Each iteration pushes `depth` elements and pops them all. The collections are created outside
+// the timed loop, so the pooled stack and the vector are warmed up after the first iteration.
+fn push_pop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push_pop");
+    for depth in [10, 1000] {
+        group.bench_with_input(BenchmarkId::new("Stack", depth), &depth, |b, &depth| {
+            let mut stack = Stack::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    stack.push(black_box(i));
+                }
+                while let Some(i) = stack.pop() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("PooledStack", depth), &depth, |b, &depth| {
+            let mut stack = PooledStack::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    stack.push(black_box(i));
+                }
+                while let Some(i) = stack.pop() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("UnrolledStack", depth), &depth, |b, &depth| {
+            let mut stack: UnrolledStack<i32> = UnrolledStack::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    stack.push(black_box(i));
+                }
+                while let Some(i) = stack.pop() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("SmallStack", depth), &depth, |b, &depth| {
+            let mut stack: SmallStack<i32> = SmallStack::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    stack.push(black_box(i));
+                }
+                while let Some(i) = stack.pop() {
+                    black_box(i);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", depth), &depth, |b, &depth| {
+            let mut vec = Vec::new();
+            b.iter(|| {
+                for i in 0..depth {
+                    vec.push(black_box(i));
+                }
+                while let Some(i) = vec.pop() {
+                    black_box(i);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, push_pop);
+criterion_main!(benches);
@@ -0,0 +1,207 @@
+use core::cmp;
+use core::fmt::{Debug, Formatter};
+use core::ops::Add;
+use crate::stack::Stack;
+
+/// Stack that knows an aggregate (e.g. the minimum, the maximum or the sum) of its elements in O(1).
+/// Each element is stored along with the aggregate of itself and all the elements below it, which
+/// stays valid no matter what happens above it. The aggregate is computed by `combine()`, which must
+/// be associative, from the values `lift()` produces for the elements, folding from the bottom up:
+/// `combine(combine(lift(e1), lift(e2)), lift(e3))`, where `e3` is the top element.
+/// Both may be closures that capture state, such as a modulus.
+///
+/// ```
+/// use rust_dust_stack::aggregate::AggregateStack;
+///
+/// let mut stack = AggregateStack::new_min();
+/// stack.push(3);
+/// stack.push(1);
+/// stack.push(2);
+/// assert_eq!(stack.peek_aggregate(), Some(&1));
+/// stack.pop();
+/// stack.pop();
+/// assert_eq!(stack.peek_aggregate(), Some(&3));
+///
+/// // Any associative function will do: here, the longest string's length.
+/// let mut stack = AggregateStack::new(|s: &String| s.len(), |a: &usize, b: &usize| *a.max(b));
+/// stack.push("three".to_string());
+/// stack.push("two".to_string());
+/// assert_eq!(stack.peek_aggregate(), Some(&5));
+///
+/// // Sum modulo a number that is only known at run time.
+/// let modulus = 7;
+/// let mut stack = AggregateStack::new(move |e: &u32| e % modulus, move |a: &u32, b: &u32| (a + b) % modulus);
+/// stack.push(5);
+/// stack.push(4);
+/// assert_eq!(stack.peek_aggregate(), Some(&2));
+/// ```
+pub struct AggregateStack<E, A, L = fn(&E) -> A, C = fn(&A, &A) -> A> {
+    stack: Stack<(E, A)>,
+    lift: L,
+    combine: C,
+}
+
+impl<E, A, L: Fn(&E) -> A, C: Fn(&A, &A) -> A> AggregateStack<E, A, L, C> {
+    /// Create an empty stack, aggregating with the given functions. Does not allocate.
+    pub fn new(lift: L, combine: C) -> Self {
+        AggregateStack{stack: Stack::new(), lift, combine}
+    }
+
+    /// Push an element on top of the stack. Calls `lift()` once and `combine()` at most once.
+    pub fn push(&mut self, elem: E) {
+        let lifted = (self.lift)(&elem);
+        let aggregate = match self.stack.peek() {
+            None => lifted,
+            Some((_, below)) => (self.combine)(below, &lifted),
+        };
+        self.stack.push((elem, aggregate));
+    }
+
+    /// Remove the top element and return it, or `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<E> {
+        self.stack.pop().map(|(elem, _)| elem)
+    }
+
+    /// The top element, if any, without removing it. There's no `peek_mut()`, as changing
+    /// the top element would invalidate its aggregate.
+    pub fn peek(&self) -> Option<&E> {
+        self.stack.peek().map(|(elem, _)| elem)
+    }
+
+    /// Aggregate of all elements, or `None` if the stack is empty.
+    pub fn peek_aggregate(&self) -> Option<&A> {
+        self.stack.peek().map(|(_, aggregate)| aggregate)
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Drop all elements.
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Iterate over the elements, top to bottom, without consuming them.
+    pub fn iter(&self) -> impl Iterator<Item=&E> {
+        self.stack.iter().map(|(elem, _)| elem)
+    }
+}
+
+impl<E: Ord + Clone> AggregateStack<E, E> {
+    /// Create an empty stack that tracks the smallest element.
+    pub fn new_min() -> Self {
+        Self::new(|elem: &E| elem.clone(), |a: &E, b: &E| cmp::min(a, b).clone())
+    }
+
+    /// Create an empty stack that tracks the largest element.
+    pub fn new_max() -> Self {
+        Self::new(|elem: &E| elem.clone(), |a: &E, b: &E| cmp::max(a, b).clone())
+    }
+}
+
+impl<E: Clone + Add<Output=E>> AggregateStack<E, E> {
+    /// Create an empty stack that tracks the sum of its elements.
+    pub fn new_sum() -> Self {
+        Self::new(|elem: &E| elem.clone(), |a: &E, b: &E| a.clone() + b.clone())
+    }
+}
+
+impl<E: Debug, A: Debug, L: Fn(&E) -> A, C: Fn(&A, &A) -> A> Debug for AggregateStack<E, A, L, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AggregateStack (aggregate: {:?}, elems: ", self.peek_aggregate())?;
+        f.debug_list().entries(self.iter()).finish()?;
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregate::AggregateStack;
+
+    #[test]
+    fn min_max_test() {
+        let mut min = AggregateStack::new_min();
+        let mut max = AggregateStack::new_max();
+        assert_eq!(min.peek_aggregate(), None);
+        assert_eq!(max.peek_aggregate(), None);
+        let elems = [5, 7, 3, 8, 3, 1, 9];
+        let expected_min = [5, 5, 3, 3, 3, 1, 1];
+        let expected_max = [5, 7, 7, 8, 8, 8, 9];
+        for (i, elem) in elems.iter().enumerate() {
+            min.push(*elem);
+            max.push(*elem);
+            assert_eq!(min.peek_aggregate(), Some(&expected_min[i]));
+            assert_eq!(max.peek_aggregate(), Some(&expected_max[i]));
+        }
+        for i in (0..elems.len()).rev() {
+            assert_eq!(min.peek_aggregate(), Some(&expected_min[i]));
+            assert_eq!(max.peek_aggregate(), Some(&expected_max[i]));
+            assert_eq!(min.pop(), Some(elems[i]));
+            assert_eq!(max.pop(), Some(elems[i]));
+        }
+        assert_eq!(min.peek_aggregate(), None);
+        assert!(min.is_empty());
+    }
+
+    #[test]
+    fn sum_test() {
+        let mut stack: AggregateStack<i64, i64> = AggregateStack::new_sum();
+        for i in 1..=100 {
+            stack.push(i);
+            assert_eq!(stack.peek_aggregate(), Some(&(i * (i + 1) / 2)));
+        }
+        assert_eq!(stack.len(), 100);
+        stack.pop();
+        assert_eq!(stack.peek_aggregate(), Some(&4950));
+        stack.clear();
+        assert_eq!(stack.peek_aggregate(), None);
+    }
+
+    #[test]
+    fn non_commutative_test() {
+        // String concatenation is associative, but not commutative: bottom comes first.
+        let mut stack = AggregateStack::new(|s: &&str| s.to_string(), |a: &String, b: &String| format!("{}{}", a, b));
+        stack.push("a");
+        stack.push("b");
+        stack.push("c");
+        assert_eq!(stack.peek_aggregate().unwrap(), "abc");
+        assert_eq!(stack.peek(), Some(&"c"));
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec!["c", "b", "a"]);
+        stack.pop();
+        assert_eq!(stack.peek_aggregate().unwrap(), "ab");
+        assert_eq!(format!("{:?}", stack), r#"AggregateStack (aggregate: Some("ab"), elems: ["b", "a"])"#);
+    }
+
+    #[test]
+    fn capturing_closure_test() {
+        // Keep the element closest to a target chosen at run time.
+        let target = 10;
+        let mut stack = AggregateStack::new(
+            |e: &i32| *e,
+            move |a: &i32, b: &i32| if (a - target).abs() <= (b - target).abs() { *a } else { *b });
+        for elem in [3, 15, 8, 12, 30] {
+            stack.push(elem);
+        }
+        assert_eq!(stack.peek_aggregate(), Some(&8));
+        stack.pop();
+        stack.pop();
+        stack.pop();
+        assert_eq!(stack.peek_aggregate(), Some(&15));
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut stack = AggregateStack::new_max();
+        for i in 0..100000 {
+            stack.push(i);
+        }
+        assert_eq!(stack.peek_aggregate(), Some(&99999));
+    }
+}
@@ -0,0 +1,398 @@
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+#[cfg(feature = "std")]
+use std::sync::{Condvar, Mutex};
+
+/// What `push()` does when the stack is full. To wait for another thread to pop an element
+/// instead, use `SyncBoundedStack::blocking()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Return the new element to the caller in `Err`.
+    Reject,
+    /// Evict the oldest (bottom) element to make room, and return it in `Ok(Some)`.
+    OverwriteOldest,
+}
+
+/// Stack that holds at most `capacity` elements.
+///
+/// Evicting the oldest element means removing the bottom node, and with the `next` links of a `Stack`,
+/// that would take a walk down the whole chain to find the node above it. So the nodes are linked
+/// both ways, with `NonNull` pointers, and the stack keeps a pointer to the bottom node as well as
+/// the top one. Every operation, including eviction, is O(1) in the worst case.
+///
+/// ```
+/// use rust_dust_stack::bounded::{BoundedStack, OverflowPolicy};
+///
+/// let mut stack = BoundedStack::new(2, OverflowPolicy::OverwriteOldest);
+/// assert_eq!(stack.push(1), Ok(None));
+/// assert_eq!(stack.push(2), Ok(None));
+/// assert_eq!(stack.push(3), Ok(Some(1)));  // Evicted
+/// assert_eq!(stack.pop(), Some(3));
+/// assert_eq!(stack.pop(), Some(2));
+/// assert_eq!(stack.pop(), None);
+///
+/// let mut stack = BoundedStack::new(1, OverflowPolicy::Reject);
+/// assert_eq!(stack.push(1), Ok(None));
+/// assert_eq!(stack.push(2), Err(2));
+/// ```
+pub struct BoundedStack<E> {
+    // Every node is allocated by `Box::new()` in `push()`, leaked, and linked in exactly once.
+    // It is owned by the stack until `pop()` or `pop_oldest()` unlinks it and turns it back
+    // into a `Box` with `Box::from_raw()`.
+    top: Option<NonNull<BoundedNode<E>>>,
+    bottom: Option<NonNull<BoundedNode<E>>>,
+    size: usize,
+    capacity: usize,
+    policy: OverflowPolicy,
+    // Tells the compiler that we own the nodes and their elements, which matters to the drop checker.
+    _nodes: PhantomData<Box<BoundedNode<E>>>,
+}
+
+struct BoundedNode<E> {
+    elem: E,
+    below: Option<NonNull<BoundedNode<E>>>,
+    above: Option<NonNull<BoundedNode<E>>>,
+}
+
+// SAFETY: the stack owns its nodes exclusively, just like a `Box` would, so it can be sent or shared
+// whenever its elements can. Raw pointers are neither `Send` nor `Sync` by default only as a precaution.
+unsafe impl<E: Send> Send for BoundedStack<E> {}
+unsafe impl<E: Sync> Sync for BoundedStack<E> {}
+
+impl<E> BoundedStack<E> {
+    /// Create an empty stack. Does not allocate. Panics if `capacity` is 0.
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        BoundedStack{top: None, bottom: None, size: 0, capacity, policy, _nodes: PhantomData}
+    }
+
+    /// Push an element on top of the stack. Returns `Ok(None)` if there was room for it;
+    /// otherwise, depending on the overflow policy, `Ok(Some(oldest))` with the element evicted
+    /// to make room, or `Err(elem)` with the rejected element.
+    pub fn push(&mut self, elem: E) -> Result<Option<E>, E> {
+        let evicted = if self.is_full() {
+            match self.policy {
+                OverflowPolicy::Reject => return Err(elem),
+                OverflowPolicy::OverwriteOldest => self.pop_oldest(),
+            }
+        } else {
+            None
+        };
+        let new_top = NonNull::from(Box::leak(Box::new(BoundedNode{elem, below: self.top, above: None})));
+        match self.top {
+            // SAFETY: `old_top` is a live node of this stack, which we borrow mutably.
+            Some(old_top) => unsafe { (*old_top.as_ptr()).above = Some(new_top) },
+            None => self.bottom = Some(new_top),
+        }
+        self.top = Some(new_top);
+        self.size += 1;
+        Ok(evicted)
+    }
+
+    /// Remove the top (newest) element and return it, or `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<E> {
+        self.top.map(|old_top| {
+            // SAFETY: `old_top` came from `Box::leak()` and is unlinked right here, so nothing
+            // points to it once we're done.
+            let old_top = unsafe { Box::from_raw(old_top.as_ptr()) };
+            self.top = old_top.below;
+            match self.top {
+                // SAFETY: `new_top` is a live node of this stack.
+                Some(new_top) => unsafe { (*new_top.as_ptr()).above = None },
+                None => self.bottom = None,
+            }
+            self.size -= 1;
+            old_top.elem
+        })
+    }
+
+    // SAFETY, for the two methods below: the node is live, and the returned reference borrows
+    // the stack, so the node cannot be freed while the reference is in use.
+
+    /// The top (newest) element, if any, without removing it.
+    pub fn peek(&self) -> Option<&E> {
+        self.top.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    /// Mutable reference to the top (newest) element, if any, without removing it.
+    pub fn peek_mut(&mut self) -> Option<&mut E> {
+        self.top.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// `true` if the next `push()` will overflow.
+    pub fn is_full(&self) -> bool {
+        self.size == self.capacity
+    }
+
+    /// Maximum number of elements this stack will hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Drop all elements, one node at a time, without recursion.
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Remove the bottom (oldest) element.
+    fn pop_oldest(&mut self) -> Option<E> {
+        self.bottom.map(|old_bottom| {
+            // SAFETY: same as in `pop()`.
+            let old_bottom = unsafe { Box::from_raw(old_bottom.as_ptr()) };
+            self.bottom = old_bottom.above;
+            match self.bottom {
+                // SAFETY: `new_bottom` is a live node of this stack.
+                Some(new_bottom) => unsafe { (*new_bottom.as_ptr()).below = None },
+                None => self.top = None,
+            }
+            self.size -= 1;
+            old_bottom.elem
+        })
+    }
+}
+
+impl<E> Drop for BoundedStack<E> {
+    // Nothing is freed unless we free it.
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// `BoundedStack` that can be shared between threads. Requires the `std` feature. A stack created
+/// with `blocking()`, rather than `new()`, makes `push()` on a full stack wait until another thread
+/// pops an element.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use rust_dust_stack::bounded::SyncBoundedStack;
+///
+/// let stack = Arc::new(SyncBoundedStack::blocking(1));
+/// stack.push(1).unwrap();
+/// let stack2 = stack.clone();
+/// let handle = thread::spawn(move || stack2.push(2));  // Blocks until 1 is popped.
+/// while stack.pop() != Some(1) {}
+/// handle.join().unwrap().unwrap();
+/// assert_eq!(stack.pop(), Some(2));
+/// ```
+#[cfg(feature = "std")]
+pub struct SyncBoundedStack<E> {
+    stack: Mutex<BoundedStack<E>>,
+    not_full: Condvar,
+    block: bool,
+}
+
+#[cfg(feature = "std")]
+impl<E> SyncBoundedStack<E> {
+    /// Create an empty stack that handles overflow according to `policy`.
+    /// Does not allocate. Panics if `capacity` is 0.
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        SyncBoundedStack{stack: Mutex::new(BoundedStack::new(capacity, policy)), not_full: Condvar::new(), block: false}
+    }
+
+    /// Create an empty stack whose `push()` waits for room when the stack is full.
+    /// Does not allocate. Panics if `capacity` is 0.
+    pub fn blocking(capacity: usize) -> Self {
+        SyncBoundedStack{stack: Mutex::new(BoundedStack::new(capacity, OverflowPolicy::Reject)), not_full: Condvar::new(), block: true}
+    }
+
+    /// Same as `BoundedStack::push()`, except that on a stack created with `blocking()`,
+    /// waits for room on a full stack, and then always returns `Ok(None)`.
+    pub fn push(&self, elem: E) -> Result<Option<E>, E> {
+        let mut stack = self.stack.lock().unwrap();
+        if self.block {
+            stack = self.not_full.wait_while(stack, |stack| stack.is_full()).unwrap();
+        }
+        stack.push(elem)
+    }
+
+    /// Remove the top (newest) element and return it, or `None` if the stack is empty.
+    pub fn pop(&self) -> Option<E> {
+        let result = self.stack.lock().unwrap().pop();
+        if result.is_some() {
+            self.not_full.notify_one();
+        }
+        result
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.stack.lock().unwrap().len()
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.stack.lock().unwrap().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounded::{BoundedStack, OverflowPolicy};
+
+    #[test]
+    fn reject_test() {
+        let mut stack = BoundedStack::new(3, OverflowPolicy::Reject);
+        assert_eq!(stack.capacity(), 3);
+        assert!(stack.is_empty());
+        for i in 0..3 {
+            assert_eq!(stack.push(i), Ok(None));
+        }
+        assert!(stack.is_full());
+        assert_eq!(stack.push(3), Err(3));
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.peek(), Some(&2));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.push(4), Ok(None));
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), Some(0));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn overwrite_test() {
+        let mut stack = BoundedStack::new(5, OverflowPolicy::OverwriteOldest);
+        for i in 0..5 {
+            assert_eq!(stack.push(i), Ok(None));
+        }
+        for i in 5..100 {
+            assert_eq!(stack.push(i), Ok(Some(i - 5)));
+            assert_eq!(stack.len(), 5);
+            assert_eq!(stack.peek(), Some(&i));
+        }
+        *stack.peek_mut().unwrap() = 1000;
+        assert_eq!(stack.pop(), Some(1000));
+        for i in (96..99).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        // Mix pops and evictions, so that both ends of the chain move.
+        assert_eq!(stack.push(200), Ok(None));
+        assert_eq!(stack.push(201), Ok(None));
+        assert_eq!(stack.push(202), Ok(None));
+        assert_eq!(stack.push(203), Ok(None));
+        assert_eq!(stack.push(204), Ok(Some(95)));
+        assert_eq!(stack.pop(), Some(204));
+        assert_eq!(stack.push(205), Ok(None));
+        assert_eq!(stack.push(206), Ok(Some(200)));
+        assert_eq!(stack.pop(), Some(206));
+        assert_eq!(stack.pop(), Some(205));
+        assert_eq!(stack.pop(), Some(203));
+        assert_eq!(stack.pop(), Some(202));
+        assert_eq!(stack.pop(), Some(201));
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn capacity_one_test() {
+        let mut stack = BoundedStack::new(1, OverflowPolicy::OverwriteOldest);
+        assert_eq!(stack.push(0), Ok(None));
+        for i in 1..10 {
+            assert_eq!(stack.push(i), Ok(Some(i - 1)));
+            assert_eq!(stack.peek(), Some(&i));
+        }
+        assert_eq!(stack.pop(), Some(9));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn against_vec_test() {
+        // Random operations checked against a `Vec` that evicts from the front.
+        use rand::Rng;
+        let mut rng = rand::rng();
+        for capacity in [1, 2, 3, 10] {
+            let mut stack = BoundedStack::new(capacity, OverflowPolicy::OverwriteOldest);
+            let mut vec = Vec::new();
+            for i in 0..10000 {
+                if rng.random_bool(0.6) {
+                    let expected = if vec.len() == capacity { Some(vec.remove(0)) } else { None };
+                    vec.push(i);
+                    assert_eq!(stack.push(i), Ok(expected));
+                } else {
+                    assert_eq!(stack.pop(), vec.pop());
+                }
+                assert_eq!(stack.len(), vec.len());
+                assert_eq!(stack.peek(), vec.last());
+            }
+        }
+    }
+
+    #[test]
+    fn ownership_test() {
+        // Every element is dropped exactly once, whether popped, evicted, cleared or dropped with the stack.
+        use std::rc::Rc;
+        let elem = Rc::new(());
+        let mut stack = BoundedStack::new(3, OverflowPolicy::OverwriteOldest);
+        for _ in 0..5 {
+            stack.push(elem.clone()).unwrap();
+        }
+        assert_eq!(Rc::strong_count(&elem), 4);
+        stack.pop();
+        assert_eq!(Rc::strong_count(&elem), 3);
+        stack.clear();
+        assert_eq!(Rc::strong_count(&elem), 1);
+        stack.push(elem.clone()).unwrap();
+        drop(stack);
+        assert_eq!(Rc::strong_count(&elem), 1);
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut stack = BoundedStack::new(100000, OverflowPolicy::Reject);
+        for i in 0..100000 {
+            stack.push(i).unwrap();
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sync_block_test() {
+        use std::sync::Arc;
+        use std::thread;
+        use crate::bounded::SyncBoundedStack;
+        let stack = Arc::new(SyncBoundedStack::blocking(2));
+        let producer = {
+            let stack = stack.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    assert_eq!(stack.push(i), Ok(None));
+                }
+            })
+        };
+        let mut popped = Vec::new();
+        while popped.len() < 1000 {
+            assert!(stack.len() <= 2);
+            if let Some(i) = stack.pop() {
+                popped.push(i);
+            }
+        }
+        producer.join().unwrap();
+        assert!(stack.is_empty());
+        popped.sort();
+        assert_eq!(popped, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sync_overwrite_test() {
+        use crate::bounded::SyncBoundedStack;
+        let stack = SyncBoundedStack::new(2, OverflowPolicy::OverwriteOldest);
+        assert_eq!(stack.push(1), Ok(None));
+        assert_eq!(stack.push(2), Ok(None));
+        assert_eq!(stack.push(3), Ok(Some(1)));
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(3));
+    }
+}
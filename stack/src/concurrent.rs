@@ -0,0 +1,278 @@
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crossbeam_epoch::{self as epoch, Atomic, Owned};
+
+/// Lock-free stack (R. K. Treiber, 1986), safe to share between threads without a `Mutex`.
+/// The head pointer is updated with an atomic compare-and-swap, retried until no other thread
+/// has changed the head in between. Popped nodes are not freed immediately, because other threads
+/// may still be reading them. Instead, they are handed to the `crossbeam_epoch` garbage collector,
+/// which frees them once all threads that could have seen them have moved on.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use rust_dust_stack::concurrent::ConcurrentStack;
+///
+/// let stack = Arc::new(ConcurrentStack::new());
+/// let handles: Vec<_> = (0..4).map(|i| {
+///     let stack = stack.clone();
+///     thread::spawn(move || stack.push(i))
+/// }).collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// let mut popped = Vec::new();
+/// while let Some(i) = stack.pop() {
+///     popped.push(i);
+/// }
+/// popped.sort();
+/// assert_eq!(popped, vec![0, 1, 2, 3]);
+/// ```
+pub struct ConcurrentStack<E> {
+    head: Atomic<ConcurrentNode<E>>,
+}
+
+struct ConcurrentNode<E> {
+    // Moved out by `pop()` with `ptr::read()` before the node is retired, so the
+    // garbage collector must not drop it again.
+    elem: ManuallyDrop<E>,
+    next: Atomic<ConcurrentNode<E>>,
+}
+
+// The elements are moved between threads, but never shared: no thread can get a reference to
+// an element it did not pop. Therefore `E: Send` is enough for both.
+unsafe impl<E: Send> Send for ConcurrentStack<E> {}
+unsafe impl<E: Send> Sync for ConcurrentStack<E> {}
+
+impl<E> ConcurrentStack<E> {
+    /// Create an empty stack. Does not allocate.
+    pub fn new() -> Self {
+        ConcurrentStack{head: Atomic::null()}
+    }
+
+    /// Push an element on top of the stack.
+    pub fn push(&self, elem: E) {
+        let mut new_node = Owned::new(ConcurrentNode{elem: ManuallyDrop::new(elem), next: Atomic::null()});
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Relaxed, &guard);
+            new_node.next.store(head, Relaxed);
+            // Release: the new node's contents must be visible to the thread that pops it.
+            match self.head.compare_exchange(head, new_node, Release, Relaxed, &guard) {
+                Ok(_) => return,
+                // Another thread got there first. Take our node back and retry.
+                Err(err) => new_node = err.new,
+            }
+        }
+    }
+
+    /// Remove the top element and return it, or `None` if the stack is empty.
+    pub fn pop(&self) -> Option<E> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Acquire, &guard);
+            // Safe to dereference: the node cannot be freed while we're pinned.
+            match unsafe { head.as_ref() } {
+                None => return None,
+                Some(node) => {
+                    let next = node.next.load(Relaxed, &guard);
+                    if self.head.compare_exchange(head, next, Relaxed, Relaxed, &guard).is_ok() {
+                        // We won the race, so we're the only thread that will ever read `elem`.
+                        unsafe {
+                            guard.defer_destroy(head);
+                            return Some(ManuallyDrop::into_inner(ptr::read(&node.elem)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `true` if the stack had no elements at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        let guard = epoch::pin();
+        self.head.load(Acquire, &guard).is_null()
+    }
+}
+
+impl<E> Drop for ConcurrentStack<E> {
+    // `&mut self` means no other thread can access the stack, so we neither need to pin,
+    // nor defer the destruction of the nodes. Like in `Stack`, a loop rather than recursion.
+    fn drop(&mut self) {
+        unsafe {
+            let guard = epoch::unprotected();
+            let mut curr_head = self.head.load(Relaxed, guard);
+            while let Some(node) = curr_head.as_ref() {
+                let next = node.next.load(Relaxed, guard);
+                let mut owned = curr_head.into_owned();
+                ManuallyDrop::drop(&mut owned.elem);
+                curr_head = next;
+            }
+        }
+    }
+}
+
+impl<E> Default for ConcurrentStack<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use crate::concurrent::ConcurrentStack;
+
+    #[test]
+    fn int_test() {
+        let stack: ConcurrentStack<i32> = ConcurrentStack::new();
+        assert!(stack.is_empty());
+        assert!(stack.pop().is_none());
+        stack.push(1);
+        stack.push(2);
+        assert!(!stack.is_empty());
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        assert!(stack.pop().is_none());
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn drop_test() {
+        let stack: ConcurrentStack<String> = ConcurrentStack::new();
+        for i in 0..100000 {
+            stack.push(i.to_string());
+        }
+    }
+
+    #[test]
+    fn stress_test() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 10000;
+        let stack = Arc::new(ConcurrentStack::new());
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let handles: Vec<_> = (0..THREADS).map(|t| {
+            let stack = stack.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                let mut popped = Vec::new();
+                for i in 0..PER_THREAD {
+                    stack.push(t * PER_THREAD + i);
+                    // Pop every other time, so that the stack grows and shrinks under contention.
+                    if i % 2 == 1 {
+                        popped.push(stack.pop().unwrap());
+                    }
+                }
+                popped
+            })
+        }).collect();
+
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for i in handle.join().unwrap() {
+                assert!(seen.insert(i), "{} popped twice", i);
+            }
+        }
+        while let Some(i) = stack.pop() {
+            assert!(seen.insert(i), "{} popped twice", i);
+        }
+        assert_eq!(seen.len(), THREADS * PER_THREAD);
+    }
+
+    #[test]
+    fn stress_drop_test() {
+        // Elements left behind in the stack are dropped with it, exactly once.
+        let elem = Arc::new(());
+        {
+            let stack = Arc::new(ConcurrentStack::new());
+            let handles: Vec<_> = (0..4).map(|_| {
+                let stack = stack.clone();
+                let elem = elem.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        stack.push(elem.clone());
+                        if i % 3 == 0 {
+                            stack.pop();
+                        }
+                    }
+                })
+            }).collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            assert!(Arc::strong_count(&elem) > 1);
+        }
+        assert_eq!(Arc::strong_count(&elem), 1);
+    }
+}
+
+/// Exhaustive interleaving tests, run with
+/// `RUSTFLAGS="--cfg crossbeam_loom" cargo test --release concurrent`
+#[cfg(all(test, crossbeam_loom))]
+mod loom_tests {
+    use loom::sync::Arc;
+    use loom::thread;
+    use crate::concurrent::ConcurrentStack;
+
+    // The epoch collector's own atomics multiply the number of interleavings,
+    // so an unbounded search takes hours. Per loom's docs, a bound of 2 or 3
+    // preemptions catches most bugs. Override with LOOM_MAX_PREEMPTIONS.
+    fn model<F: Fn() + Sync + Send + 'static>(f: F) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound.get_or_insert(3);
+        builder.check(f);
+    }
+
+    #[test]
+    fn concurrent_push() {
+        model(|| {
+            let stack = Arc::new(ConcurrentStack::new());
+            let stack2 = stack.clone();
+            let handle = thread::spawn(move || stack2.push(1));
+            stack.push(2);
+            handle.join().unwrap();
+            let mut popped = vec![stack.pop().unwrap(), stack.pop().unwrap()];
+            popped.sort();
+            assert_eq!(popped, vec![1, 2]);
+            assert!(stack.is_empty());
+        });
+    }
+
+    #[test]
+    fn concurrent_pop() {
+        model(|| {
+            let stack = Arc::new(ConcurrentStack::new());
+            stack.push(1);
+            stack.push(2);
+            let stack2 = stack.clone();
+            let handle = thread::spawn(move || stack2.pop());
+            let mine = stack.pop();
+            let theirs = handle.join().unwrap();
+            let mut popped = vec![mine.unwrap(), theirs.unwrap()];
+            popped.sort();
+            assert_eq!(popped, vec![1, 2]);
+            assert!(stack.pop().is_none());
+        });
+    }
+
+    #[test]
+    fn concurrent_push_pop() {
+        model(|| {
+            let stack = Arc::new(ConcurrentStack::new());
+            let stack2 = stack.clone();
+            let handle = thread::spawn(move || {
+                stack2.push(1);
+                stack2.pop().unwrap()
+            });
+            stack.push(2);
+            let mine = stack.pop().unwrap();
+            let theirs = handle.join().unwrap();
+            assert_ne!(mine, theirs);
+            assert!(stack.pop().is_none());
+        });
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use rust_dust_lib::token::Tokenizer;
+use crate::stack::Stack;
+
+/// Why an expression could not be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    DivisionByZero,
+    MismatchedParentheses,
+    /// A character that is not part of any token, e.g. `$` or `&`.
+    UnknownOperator(String),
+    UnknownVariable(String),
+    /// A number literal that does not parse, e.g. `1.2.3`.
+    InvalidNumber(String),
+    /// An operator without enough operands, operands without an operator between them,
+    /// or nothing at all.
+    Malformed,
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprError::DivisionByZero => write!(f, "division by zero"),
+            ExprError::MismatchedParentheses => write!(f, "mismatched parentheses"),
+            ExprError::UnknownOperator(op) => write!(f, "unknown operator '{}'", op),
+            ExprError::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            ExprError::InvalidNumber(number) => write!(f, "invalid number '{}'", number),
+            ExprError::Malformed => write!(f, "malformed expression"),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// Arithmetic operator. `Neg` is the unary minus, which has its own symbol, `~`, in RPN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Neg,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Operator> {
+        match c {
+            '+' => Some(Operator::Add),
+            '-' => Some(Operator::Sub),
+            '*' => Some(Operator::Mul),
+            '/' => Some(Operator::Div),
+            '%' => Some(Operator::Rem),
+            '^' => Some(Operator::Pow),
+            '~' => Some(Operator::Neg),
+            _ => None,
+        }
+    }
+
+    /// Binds tighter than lower precedence operators. Unary minus binds looser than `^`,
+    /// so that `-2^2` is `-4`, as in math.
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Sub => 1,
+            Operator::Mul | Operator::Div | Operator::Rem => 2,
+            Operator::Neg => 3,
+            Operator::Pow => 4,
+        }
+    }
+
+    fn is_right_associative(self) -> bool {
+        matches!(self, Operator::Pow | Operator::Neg)
+    }
+
+    /// Pop this operator's operands off `operands` and push the result.
+    fn apply(self, operands: &mut Stack<f64>) -> Result<(), ExprError> {
+        let right = operands.pop().ok_or(ExprError::Malformed)?;
+        if self == Operator::Neg {
+            operands.push(-right);
+            return Ok(());
+        }
+        let left = operands.pop().ok_or(ExprError::Malformed)?;
+        let result = match self {
+            Operator::Add => left + right,
+            Operator::Sub => left - right,
+            Operator::Mul => left * right,
+            Operator::Div | Operator::Rem if right == 0.0 => return Err(ExprError::DivisionByZero),
+            Operator::Div => left / right,
+            Operator::Rem => left % right,
+            Operator::Pow => left.powf(right),
+            Operator::Neg => unreachable!(),
+        };
+        operands.push(result);
+        Ok(())
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Operator::Add => '+',
+            Operator::Sub => '-',
+            Operator::Mul => '*',
+            Operator::Div => '/',
+            Operator::Rem => '%',
+            Operator::Pow => '^',
+            Operator::Neg => '~',
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Variable(String),
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Variable(name) => write!(f, "{}", name),
+            Token::Operator(op) => write!(f, "{}", op),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+        }
+    }
+}
+
+/// Split an expression into tokens. The `Tokenizer` splits it on white space, and each of
+/// the resulting words is split further, so that `2*(x+1)` and `2 * ( x + 1 )` are the same.
+pub fn tokenize(expr: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    for word in Tokenizer::new().from_buf_reader(expr.as_bytes()) {
+        let mut chars = word.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = if c.is_ascii_digit() || c == '.' {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                Token::Number(number.parse().map_err(|_| ExprError::InvalidNumber(number))?)
+            } else if c.is_alphabetic() || c == '_' {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                Token::Variable(name)
+            } else if c == '(' {
+                Token::LeftParen
+            } else if c == ')' {
+                Token::RightParen
+            } else {
+                Token::Operator(Operator::from_char(c).ok_or(ExprError::UnknownOperator(c.to_string()))?)
+            };
+            tokens.push(token);
+        }
+    }
+    Ok(tokens)
+}
+
+/// Convert infix tokens to reverse Polish notation (RPN) with Dijkstra's shunting-yard algorithm.
+/// Operands go straight to the output, while operators wait on a stack until an operator
+/// with lower precedence, a closing parenthesis or the end of the input pops them off it.
+/// A `-` where an operand is expected is the unary minus, and is converted to `Neg`.
+pub fn to_rpn(tokens: Vec<Token>) -> Result<Vec<Token>, ExprError> {
+    let mut output = Vec::with_capacity(tokens.len());
+    // Holds only operators and left parentheses.
+    let mut pending: Stack<Token> = Stack::new();
+    let mut expect_operand = true;
+    for token in tokens {
+        match token {
+            Token::Number(_) | Token::Variable(_) => {
+                if !expect_operand {
+                    return Err(ExprError::Malformed);
+                }
+                output.push(token);
+                expect_operand = false;
+            }
+            Token::LeftParen => {
+                if !expect_operand {
+                    return Err(ExprError::Malformed);
+                }
+                pending.push(token);
+            }
+            Token::RightParen => {
+                loop {
+                    match pending.pop() {
+                        None => return Err(ExprError::MismatchedParentheses),
+                        Some(Token::LeftParen) => break,
+                        Some(op) => output.push(op),
+                    }
+                }
+                expect_operand = false;
+            }
+            Token::Operator(op) if expect_operand => match op {
+                // Prefix operators have nothing to their left to pop.
+                Operator::Sub | Operator::Neg => pending.push(Token::Operator(Operator::Neg)),
+                Operator::Add => {}
+                _ => return Err(ExprError::Malformed),
+            },
+            Token::Operator(op) => {
+                while let Some(Token::Operator(top)) = pending.peek() {
+                    if top.precedence() > op.precedence()
+                        || (top.precedence() == op.precedence() && !op.is_right_associative()) {
+                        output.push(pending.pop().unwrap());
+                    } else {
+                        break;
+                    }
+                }
+                pending.push(token);
+                expect_operand = true;
+            }
+        }
+    }
+    for token in pending.drain() {
+        if token == Token::LeftParen {
+            return Err(ExprError::MismatchedParentheses);
+        }
+        output.push(token);
+    }
+    Ok(output)
+}
+
+/// Arithmetic expression evaluator with named variables. All numbers are `f64`.
+///
+/// ```
+/// use rust_dust_stack::expr::{Evaluator, ExprError};
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.set("width", 1.5);
+/// assert_eq!(evaluator.eval("2 * (width + 1) ^ 2"), Ok(12.5));
+/// assert_eq!(evaluator.eval_rpn("2 width 1 + 2 ^ *"), Ok(12.5));
+/// assert_eq!(evaluator.eval("1 / (width - 1.5)"), Err(ExprError::DivisionByZero));
+/// assert_eq!(evaluator.eval("height"), Err(ExprError::UnknownVariable("height".to_string())));
+/// ```
+#[derive(Debug, Default)]
+pub struct Evaluator {
+    variables: HashMap<String, f64>,
+}
+
+impl Evaluator {
+    /// Create an evaluator with no variables.
+    pub fn new() -> Self {
+        Evaluator{variables: HashMap::new()}
+    }
+
+    /// Set the value of a variable, returning its previous value, if any.
+    pub fn set(&mut self, name: &str, value: f64) -> Option<f64> {
+        self.variables.insert(name.to_string(), value)
+    }
+
+    /// The value of a variable, if it is set.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    /// Evaluate an infix expression, e.g. `-(x + 1) * 2`.
+    pub fn eval(&self, expr: &str) -> Result<f64, ExprError> {
+        self.eval_tokens(&to_rpn(tokenize(expr)?)?)
+    }
+
+    /// Evaluate an expression in reverse Polish notation, e.g. `x 1 + ~ 2 *`.
+    /// Operands must be separated by white space. `-` is always binary; `~` is the unary minus.
+    pub fn eval_rpn(&self, expr: &str) -> Result<f64, ExprError> {
+        self.eval_tokens(&tokenize(expr)?)
+    }
+
+    /// Evaluate tokens in RPN: push operands on a stack, and replace the operands of each operator
+    /// with the result. In the end, a well-formed expression leaves exactly one value on the stack.
+    pub fn eval_tokens(&self, rpn: &[Token]) -> Result<f64, ExprError> {
+        let mut operands: Stack<f64> = Stack::new();
+        for token in rpn {
+            match token {
+                Token::Number(number) => operands.push(*number),
+                Token::Variable(name) => {
+                    let value = self.get(name).ok_or_else(|| ExprError::UnknownVariable(name.clone()))?;
+                    operands.push(value);
+                }
+                Token::Operator(op) => op.apply(&mut operands)?,
+                Token::LeftParen | Token::RightParen => return Err(ExprError::MismatchedParentheses),
+            }
+        }
+        match (operands.pop(), operands.is_empty()) {
+            (Some(result), true) => Ok(result),
+            _ => Err(ExprError::Malformed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::{tokenize, to_rpn, Evaluator, ExprError};
+
+    fn rpn(expr: &str) -> String {
+        to_rpn(tokenize(expr).unwrap()).unwrap().iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn rpn_test() {
+        assert_eq!(rpn("1 + 2 * 3"), "1 2 3 * +");
+        assert_eq!(rpn("(1+2)*3"), "1 2 + 3 *");
+        assert_eq!(rpn("8 - 3 - 2"), "8 3 - 2 -");
+        assert_eq!(rpn("2 ^ 3 ^ 2"), "2 3 2 ^ ^");
+        assert_eq!(rpn("-2^2"), "2 2 ^ ~");
+        assert_eq!(rpn("-x * -(y % 4)"), "x ~ y 4 % ~ *");
+    }
+
+    #[test]
+    fn eval_test() {
+        let evaluator = Evaluator::new();
+        assert_eq!(evaluator.eval("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(evaluator.eval("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(evaluator.eval("8 - 3 - 2"), Ok(3.0));
+        assert_eq!(evaluator.eval("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(evaluator.eval("-2 ^ 2"), Ok(-4.0));
+        assert_eq!(evaluator.eval("2 ^ -1"), Ok(0.5));
+        assert_eq!(evaluator.eval("--3"), Ok(3.0));
+        assert_eq!(evaluator.eval("+3 - -3"), Ok(6.0));
+        assert_eq!(evaluator.eval("7 % 4 * 0.5"), Ok(1.5));
+        assert_eq!(evaluator.eval("((((1))))\n+\n2"), Ok(3.0));
+        assert_eq!(evaluator.eval_rpn("3 4 + 2 *"), Ok(14.0));
+        assert_eq!(evaluator.eval_rpn("3 ~ 4 -"), Ok(-7.0));
+    }
+
+    #[test]
+    fn variable_test() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.set("x", 2.0), None);
+        assert_eq!(evaluator.set("x", 3.0), Some(2.0));
+        evaluator.set("rate_2", 0.5);
+        assert_eq!(evaluator.get("x"), Some(3.0));
+        assert_eq!(evaluator.eval("x*x - rate_2"), Ok(8.5));
+        assert_eq!(evaluator.eval("x * y"), Err(ExprError::UnknownVariable("y".to_string())));
+    }
+
+    #[test]
+    fn error_test() {
+        let evaluator = Evaluator::new();
+        assert_eq!(evaluator.eval("1 / 0"), Err(ExprError::DivisionByZero));
+        assert_eq!(evaluator.eval("1 % (2 - 2)"), Err(ExprError::DivisionByZero));
+        assert_eq!(evaluator.eval("(1 + 2"), Err(ExprError::MismatchedParentheses));
+        assert_eq!(evaluator.eval("1 + 2)"), Err(ExprError::MismatchedParentheses));
+        assert_eq!(evaluator.eval("1 & 2"), Err(ExprError::UnknownOperator("&".to_string())));
+        assert_eq!(evaluator.eval("1.2.3"), Err(ExprError::InvalidNumber("1.2.3".to_string())));
+        assert_eq!(evaluator.eval(""), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval("1 +"), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval("* 1"), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval("1 2"), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval("()"), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval_rpn("1 +"), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval_rpn("1 2"), Err(ExprError::Malformed));
+        assert_eq!(evaluator.eval_rpn("1 ( 2 +"), Err(ExprError::MismatchedParentheses));
+        assert_eq!(ExprError::UnknownVariable("y".to_string()).to_string(), "unknown variable 'y'");
+    }
+}
@@ -0,0 +1,443 @@
+use crate::bounded::{BoundedStack, OverflowPolicy};
+use crate::stack::Stack;
+
+/// Reversible operation on some target, e.g. an edit to a document.
+pub trait Command {
+    type Target;
+
+    /// Perform the operation.
+    fn apply(&mut self, target: &mut Self::Target);
+
+    /// Reverse the effect of `apply()`.
+    fn undo(&mut self, target: &mut Self::Target);
+
+    /// Try to absorb the `next` command, which has just been applied, into this one,
+    /// so that both are undone as a single step. For example, consecutive keystrokes
+    /// can be merged into one insertion. Returns `next` back in `Err` if it cannot be merged,
+    /// which is the default.
+    fn merge(&mut self, next: Self) -> Result<(), Self> where Self: Sized {
+        Err(next)
+    }
+}
+
+/// One undo step: either a single command, or a group of commands, which are undone together.
+struct Entry<C> {
+    // Top is the command to be undone first in the undo stack, or redone first in the redo stack.
+    commands: Stack<C>,
+    // Unique ID of the state the target is in after this entry has been applied.
+    id: u64,
+    group: bool,
+}
+
+/// Undo/redo history of the commands applied to some target. Each `undo()` pops an entry off
+/// the undo stack and pushes it on the redo stack; each `redo()` does the opposite. Executing a new
+/// command clears the redo stack.
+///
+/// ```
+/// use rust_dust_stack::history::{Command, History};
+///
+/// struct Add(i32);
+/// impl Command for Add {
+///     type Target = i32;
+///     fn apply(&mut self, target: &mut i32) { *target += self.0; }
+///     fn undo(&mut self, target: &mut i32) { *target -= self.0; }
+/// }
+///
+/// let mut target = 0;
+/// let mut history = History::new();
+/// history.execute(&mut target, Add(1));
+/// history.execute(&mut target, Add(2));
+/// assert_eq!(target, 3);
+/// assert!(history.undo(&mut target));
+/// assert_eq!(target, 1);
+/// assert!(history.redo(&mut target));
+/// assert_eq!(target, 3);
+/// ```
+pub struct History<C: Command> {
+    undo_stack: BoundedStack<Entry<C>>,
+    redo_stack: Stack<Entry<C>>,
+    // Group being recorded and its nesting depth.
+    open_group: Option<Entry<C>>,
+    group_depth: usize,
+    // ID of the state below the bottom of the undo stack.
+    base_id: u64,
+    saved_id: Option<u64>,
+    last_id: u64,
+}
+
+impl<C: Command> History<C> {
+    /// Create an empty history with unlimited depth.
+    pub fn new() -> Self {
+        Self::new_with_max_depth(usize::MAX)
+    }
+
+    /// Create an empty history, which remembers at most `max_depth` undo steps,
+    /// forgetting the oldest ones. Panics if `max_depth` is 0.
+    pub fn new_with_max_depth(max_depth: usize) -> Self {
+        History{
+            undo_stack: BoundedStack::new(max_depth, OverflowPolicy::OverwriteOldest),
+            redo_stack: Stack::new(),
+            open_group: None,
+            group_depth: 0,
+            base_id: 0,
+            saved_id: None,
+            last_id: 0,
+        }
+    }
+
+    /// Apply `command` to `target` and record it in the history. If a group is open, the command
+    /// is added to it. Otherwise, it is merged into the previous command, if possible and if that
+    /// is not the saved step, or else it becomes a new undo step. Clears the redo history.
+    pub fn execute(&mut self, target: &mut C::Target, mut command: C) {
+        command.apply(target);
+        self.redo_stack.clear();
+        let new_id = self.next_id();
+        // Merging into the saved step would change its ID, and the save point could not be reached again.
+        let saved_id = self.saved_id;
+        let top = match &mut self.open_group {
+            Some(group) => Some(group),
+            None => self.undo_stack.peek_mut().filter(|entry| !entry.group && Some(entry.id) != saved_id),
+        };
+        if let Some(entry) = top && let Some(last) = entry.commands.peek_mut() {
+            match last.merge(command) {
+                Ok(()) => {
+                    // Same entry, but a different state.
+                    entry.id = new_id;
+                    return;
+                }
+                Err(unmerged) => command = unmerged,
+            }
+        }
+        match &mut self.open_group {
+            Some(group) => group.commands.push(command),
+            None => self.push_entry(Entry{commands: Stack::from_iter([command]), id: new_id, group: false}),
+        }
+    }
+
+    /// Start a group: the commands executed until the matching `end_group()` will be undone and
+    /// redone together, as a single step. Groups may be nested; only the outermost group counts.
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.open_group = Some(Entry{commands: Stack::new(), id: 0, group: true});
+        }
+        self.group_depth += 1;
+    }
+
+    /// End the group started by the matching `begin_group()`. Ending the outermost group
+    /// records it as a single undo step, unless it is empty. Does nothing if no group is open.
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+        self.group_depth -= 1;
+        if self.group_depth == 0 {
+            let mut group = self.open_group.take().unwrap();
+            if !group.commands.is_empty() {
+                group.id = self.next_id();
+                self.push_entry(group);
+            }
+        }
+    }
+
+    /// Undo the last step. Closes any open groups first. Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self, target: &mut C::Target) -> bool {
+        self.close_groups();
+        match self.undo_stack.pop() {
+            None => false,
+            Some(entry) => {
+                let entry = Self::replay(entry, |command| command.undo(target));
+                self.redo_stack.push(entry);
+                true
+            }
+        }
+    }
+
+    /// Redo the last undone step. Closes any open groups first.
+    /// Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self, target: &mut C::Target) -> bool {
+        self.close_groups();
+        match self.redo_stack.pop() {
+            None => false,
+            Some(entry) => {
+                let entry = Self::replay(entry, |command| command.apply(target));
+                self.push_entry(entry);
+                true
+            }
+        }
+    }
+
+    /// `true` if there is a step to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.open_group.as_ref().is_some_and(|group| !group.commands.is_empty())
+    }
+
+    /// `true` if there is a step to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Number of steps that can be undone, not counting an open group.
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Number of steps that can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    /// Remember the current state of the target as saved.
+    pub fn mark_saved(&mut self) {
+        self.saved_id = self.current_id();
+    }
+
+    /// `true` if the target is in the state it was in when `mark_saved()` was last called,
+    /// either because nothing has changed since, or because all changes have been undone or redone.
+    pub fn is_saved(&self) -> bool {
+        self.saved_id.is_some() && self.saved_id == self.current_id()
+    }
+
+    /// Forget all undo and redo steps, and any open groups. Does not affect the saved state.
+    pub fn clear(&mut self) {
+        self.close_groups();
+        self.base_id = self.current_id().unwrap();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// ID of the target's current state, or `None` if it's in the middle of a group.
+    fn current_id(&self) -> Option<u64> {
+        match &self.open_group {
+            Some(group) if !group.commands.is_empty() => None,
+            _ => Some(self.undo_stack.peek().map_or(self.base_id, |entry| entry.id)),
+        }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn push_entry(&mut self, entry: Entry<C>) {
+        if let Ok(Some(evicted)) = self.undo_stack.push(entry) {
+            // The state before the oldest remaining step is now the one after the evicted step.
+            self.base_id = evicted.id;
+        }
+    }
+
+    fn close_groups(&mut self) {
+        while self.group_depth > 0 {
+            self.end_group();
+        }
+    }
+
+    /// Run `action` on each command in the entry, top to bottom. Popping the commands off one stack
+    /// and pushing them on another reverses their order, which is what the next replay will need.
+    fn replay(entry: Entry<C>, mut action: impl FnMut(&mut C)) -> Entry<C> {
+        let Entry{mut commands, id, group} = entry;
+        let mut replayed = Stack::new();
+        while let Some(mut command) = commands.pop() {
+            action(&mut command);
+            replayed.push(command);
+        }
+        Entry{commands: replayed, id, group}
+    }
+}
+
+impl<C: Command> Default for History<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{Command, History};
+
+    /// Text editing commands, with consecutive insertions merged, as an editor would.
+    #[derive(Debug)]
+    enum Edit {
+        Insert(usize, String),
+        Delete(usize, String),
+    }
+
+    impl Edit {
+        fn delete(target: &str, at: usize, len: usize) -> Edit {
+            Edit::Delete(at, target[at..at + len].to_string())
+        }
+    }
+
+    impl Command for Edit {
+        type Target = String;
+        fn apply(&mut self, target: &mut String) {
+            match self {
+                Edit::Insert(at, text) => target.insert_str(*at, text),
+                Edit::Delete(at, text) => target.replace_range(*at..*at + text.len(), ""),
+            }
+        }
+        fn undo(&mut self, target: &mut String) {
+            match self {
+                Edit::Insert(at, text) => target.replace_range(*at..*at + text.len(), ""),
+                Edit::Delete(at, text) => target.insert_str(*at, text),
+            }
+        }
+        fn merge(&mut self, next: Self) -> Result<(), Self> {
+            match (self, next) {
+                (Edit::Insert(at, text), Edit::Insert(next_at, next_text)) if *at + text.len() == next_at => {
+                    text.push_str(&next_text);
+                    Ok(())
+                }
+                (_, next) => Err(next),
+            }
+        }
+    }
+
+    #[test]
+    fn undo_redo_test() {
+        let mut doc = String::new();
+        let mut history = History::new();
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut doc));
+        assert!(!history.redo(&mut doc));
+        history.execute(&mut doc, Edit::Insert(0, "hello".to_string()));
+        history.execute(&mut doc, Edit::Insert(0, "oh, ".to_string()));
+        let delete = Edit::delete(&doc, 0, 2);
+        history.execute(&mut doc, delete);
+        assert_eq!(doc, ", hello");
+        assert_eq!(history.undo_len(), 3);
+        assert!(history.undo(&mut doc));
+        assert_eq!(doc, "oh, hello");
+        assert!(history.undo(&mut doc));
+        assert_eq!(doc, "hello");
+        assert!(history.can_redo());
+        assert_eq!(history.redo_len(), 2);
+        assert!(history.redo(&mut doc));
+        assert_eq!(doc, "oh, hello");
+        // New command clears redo history.
+        history.execute(&mut doc, Edit::Insert(0, "Oh".to_string()));
+        assert_eq!(doc, "Ohoh, hello");
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut doc));
+        while history.undo(&mut doc) {}
+        assert_eq!(doc, "");
+        while history.redo(&mut doc) {}
+        assert_eq!(doc, "Ohoh, hello");
+    }
+
+    #[test]
+    fn merge_test() {
+        let mut doc = String::new();
+        let mut history = History::new();
+        for (i, c) in "type".chars().enumerate() {
+            history.execute(&mut doc, Edit::Insert(i, c.to_string()));
+        }
+        history.execute(&mut doc, Edit::Insert(0, "we ".to_string()));
+        assert_eq!(doc, "we type");
+        assert_eq!(history.undo_len(), 2);
+        history.undo(&mut doc);
+        assert_eq!(doc, "type");
+        history.undo(&mut doc);
+        assert_eq!(doc, "");
+        history.redo(&mut doc);
+        assert_eq!(doc, "type");
+    }
+
+    #[test]
+    fn group_test() {
+        let mut doc = "abc".to_string();
+        let mut history = History::new();
+        history.begin_group();
+        let delete = Edit::delete(&doc, 1, 1);
+        history.execute(&mut doc, delete);
+        history.begin_group();
+        history.execute(&mut doc, Edit::Insert(1, "B".to_string()));
+        history.end_group();
+        assert_eq!(history.undo_len(), 0);
+        assert!(history.can_undo());
+        history.execute(&mut doc, Edit::Insert(0, "A".to_string()));
+        history.end_group();
+        assert_eq!(doc, "AaBc");
+        assert_eq!(history.undo_len(), 1);
+        history.undo(&mut doc);
+        assert_eq!(doc, "abc");
+        history.redo(&mut doc);
+        assert_eq!(doc, "AaBc");
+        // A new command is not merged into the group.
+        history.execute(&mut doc, Edit::Insert(4, "d".to_string()));
+        assert_eq!(history.undo_len(), 2);
+        // Empty group is not recorded.
+        history.begin_group();
+        history.end_group();
+        history.end_group();
+        assert_eq!(history.undo_len(), 2);
+        // Undo closes the open group.
+        history.begin_group();
+        history.execute(&mut doc, Edit::Insert(0, "x".to_string()));
+        history.execute(&mut doc, Edit::Insert(0, "y".to_string()));
+        assert_eq!(doc, "yxAaBcd");
+        history.undo(&mut doc);
+        assert_eq!(doc, "AaBcd");
+        assert_eq!(history.undo_len(), 2);
+    }
+
+    #[test]
+    fn max_depth_test() {
+        let mut doc = String::new();
+        let mut history = History::new_with_max_depth(3);
+        for i in 0..10 {
+            history.execute(&mut doc, Edit::Insert(0, i.to_string()));
+        }
+        assert_eq!(doc, "9876543210");
+        assert_eq!(history.undo_len(), 3);
+        while history.undo(&mut doc) {}
+        assert_eq!(doc, "6543210");
+    }
+
+    #[test]
+    fn save_point_test() {
+        let mut doc = String::new();
+        let mut history = History::new_with_max_depth(2);
+        assert!(!history.is_saved());
+        history.mark_saved();
+        assert!(history.is_saved());
+        history.execute(&mut doc, Edit::Insert(0, "a".to_string()));
+        assert!(!history.is_saved());
+        history.undo(&mut doc);
+        assert!(history.is_saved());
+        history.redo(&mut doc);
+        history.mark_saved();
+        // Not merged into the saved step, so one undo gets back to it.
+        history.execute(&mut doc, Edit::Insert(1, "b".to_string()));
+        assert_eq!(doc, "ab");
+        assert!(!history.is_saved());
+        history.undo(&mut doc);
+        assert_eq!(doc, "a");
+        assert!(history.is_saved());
+        history.undo(&mut doc);
+        assert!(!history.is_saved());
+        assert_eq!(doc, "");
+        history.mark_saved();
+        // Saved state is evicted, then reached again by undoing.
+        history.execute(&mut doc, Edit::Insert(0, "x".to_string()));
+        history.execute(&mut doc, Edit::Insert(0, "y".to_string()));
+        history.execute(&mut doc, Edit::Insert(0, "z".to_string()));
+        assert!(!history.is_saved());
+        while history.undo(&mut doc) {}
+        assert_eq!(doc, "x");
+        assert!(!history.is_saved());
+        // In the middle of a group.
+        history.mark_saved();
+        history.begin_group();
+        assert!(history.is_saved());
+        history.execute(&mut doc, Edit::Insert(0, "w".to_string()));
+        assert!(!history.is_saved());
+        history.end_group();
+        history.undo(&mut doc);
+        assert!(history.is_saved());
+        // Clearing keeps the saved state.
+        history.clear();
+        assert!(history.is_saved());
+        assert!(!history.can_undo());
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,26 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+// mod naive;
+#[cfg(feature = "alloc")]
+pub mod stack;
+#[cfg(feature = "alloc")]
+pub mod persistent;
+#[cfg(feature = "std")]
+pub mod concurrent;
+#[cfg(feature = "alloc")]
+pub mod pooled;
+#[cfg(feature = "alloc")]
+pub mod unrolled;
+#[cfg(feature = "alloc")]
+pub mod small;
+#[cfg(feature = "alloc")]
+pub mod aggregate;
+#[cfg(feature = "alloc")]
+pub mod bounded;
+#[cfg(feature = "alloc")]
+pub mod history;
+#[cfg(feature = "std")]
+pub mod expr;
@@ -1,4 +0,0 @@
-
-// mod naive;
-mod stack;
-fn main() {}
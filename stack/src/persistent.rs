@@ -0,0 +1,210 @@
+use alloc::rc::Rc;
+use core::fmt::{Debug, Formatter};
+
+/// Persistent (immutable) stack. `push()` and `pop()` leave `self` intact and return a new version
+/// of the stack, which shares all of its nodes below the top with the old version. Both are O(1).
+///
+/// ```
+/// use rust_dust_stack::persistent::PersistentStack;
+///
+/// let empty = PersistentStack::new();
+/// let one = empty.push(1);
+/// let two = one.push(2);
+/// let (top, rest) = two.pop().unwrap();
+/// assert_eq!(*top, 2);
+/// assert_eq!(rest.peek(), Some(&1));
+/// // Old versions are still valid.
+/// assert_eq!(two.len(), 2);
+/// assert_eq!(one.len(), 1);
+/// assert!(empty.is_empty());
+/// ```
+pub struct PersistentStack<E> {
+    head: Option<Rc<PersistentNode<E>>>,
+    size: usize,
+}
+
+struct PersistentNode<E> {
+    elem: E,
+    next: Option<Rc<PersistentNode<E>>>,
+}
+
+impl<E> PersistentStack<E> {
+    /// Create an empty stack. Does not allocate.
+    pub fn new() -> Self {
+        PersistentStack{head: None, size: 0}
+    }
+
+    /// New version of this stack with `elem` on top.
+    pub fn push(&self, elem: E) -> Self {
+        let new_node = Rc::new(PersistentNode{elem, next: self.head.clone()});
+        PersistentStack{head: Some(new_node), size: self.size + 1}
+    }
+
+    /// The top element and the new version of this stack without it, or `None` if the stack is empty.
+    pub fn pop(&self) -> Option<(&E, Self)> {
+        self.head.as_ref().map(|node| {
+            (&node.elem, PersistentStack{head: node.next.clone(), size: self.size - 1})
+        })
+    }
+
+    /// The top element, if any.
+    pub fn peek(&self) -> Option<&E> {
+        self.head.as_ref().map(|node| &node.elem)
+    }
+
+    /// Number of elements in this version of the stack.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if this version of the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Iterate over the elements, top to bottom.
+    pub fn iter(&self) -> Iter<'_, E> {
+        Iter { next: self.head.as_deref() }
+    }
+}
+
+impl<E> Drop for PersistentStack<E> {
+    // Like in `Stack`, the compiler-generated `drop()` would be recursive. But unlike in `Stack`,
+    // we can only unlink nodes that are not shared with another version of the stack. As soon as we
+    // reach a node that has another referrer, the rest of the chain belongs to that version as well
+    // and we stop.
+    fn drop(&mut self) {
+        let mut curr_head = self.head.take();
+        while let Some(rc_node) = curr_head {
+            match Rc::try_unwrap(rc_node) {
+                Ok(mut node) => curr_head = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<E> Default for PersistentStack<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cloning a version is O(1): it only clones the `Rc` of the top node.
+impl<E> Clone for PersistentStack<E> {
+    fn clone(&self) -> Self {
+        PersistentStack{head: self.head.clone(), size: self.size}
+    }
+}
+
+impl<E: Debug> Debug for PersistentStack<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Elements are pushed in iteration order, so the last one ends up on top.
+impl<E> FromIterator<E> for PersistentStack<E> {
+    fn from_iter<I: IntoIterator<Item=E>>(iter: I) -> Self {
+        let mut stack = PersistentStack::new();
+        for elem in iter {
+            stack = stack.push(elem);
+        }
+        stack
+    }
+}
+
+/// Borrowing iterator, returned by `PersistentStack::iter()`.
+pub struct Iter<'a, E> {
+    next: Option<&'a PersistentNode<E>>,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            &node.elem
+        })
+    }
+}
+
+impl<'a, E> IntoIterator for &'a PersistentStack<E> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persistent::PersistentStack;
+
+    #[test]
+    fn int_test() {
+        let stack: PersistentStack<i32> = PersistentStack::new();
+        assert!(stack.pop().is_none());
+        assert_eq!(0, stack.len());
+        let stack1 = stack.push(1);
+        assert_eq!(1, stack1.len());
+        let (top, stack2) = stack1.pop().unwrap();
+        assert_eq!(1, *top);
+        assert_eq!(0, stack2.len());
+        assert!(stack2.pop().is_none());
+        // The old versions are unchanged.
+        assert_eq!(0, stack.len());
+        assert_eq!(Some(&1), stack1.peek());
+    }
+
+    #[test]
+    fn sharing_test() {
+        let base: PersistentStack<String> = (0..10).map(|i| i.to_string()).collect();
+        let left = base.push("left".to_string());
+        let right = base.pop().unwrap().1.push("right".to_string());
+        assert_eq!(base.len(), 10);
+        assert_eq!(left.len(), 11);
+        assert_eq!(right.len(), 10);
+        assert_eq!(
+            left.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+            vec!["left", "9", "8", "7", "6", "5", "4", "3", "2", "1", "0"]
+        );
+        assert_eq!(
+            right.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+            vec!["right", "8", "7", "6", "5", "4", "3", "2", "1", "0"]
+        );
+        drop(base);
+        drop(left);
+        // Shared tail survives the versions that are gone.
+        assert_eq!(format!("{:?}", right.pop().unwrap().1), r#"["8", "7", "6", "5", "4", "3", "2", "1", "0"]"#);
+    }
+
+    #[test]
+    fn backtrack_test() {
+        // Keep every version, as a backtracking search would.
+        let mut versions: Vec<PersistentStack<i32>> = vec![PersistentStack::new()];
+        for i in 0..100 {
+            let next = versions.last().unwrap().push(i);
+            versions.push(next);
+        }
+        for (i, version) in versions.iter().enumerate() {
+            assert_eq!(version.len(), i);
+            assert_eq!(version.iter().copied().collect::<Vec<_>>(), (0..i as i32).rev().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut stack: PersistentStack<i32> = PersistentStack::new();
+        for i in 0..100000 {
+            stack = stack.push(i);
+        }
+        let snapshot = stack.clone();
+        drop(stack);
+        assert_eq!(snapshot.len(), 100000);
+        // Stops at the first shared node.
+        let branch = snapshot.pop().unwrap().1.push(-1);
+        drop(branch);
+        assert_eq!(snapshot.peek(), Some(&99999));
+    }
+}
@@ -0,0 +1,221 @@
+use alloc::boxed::Box;
+use crate::stack::StackNode;
+
+/// Stack that recycles its nodes. `pop()` does not free the popped node, but keeps it on a free
+/// list, from which `push()` takes it back, so a push-pop loop with a steady depth does no heap
+/// allocations after warm-up. At most `retention` free nodes are kept around; the rest are freed.
+///
+/// ```
+/// use rust_dust_stack::pooled::PooledStack;
+///
+/// let mut stack = PooledStack::new_with_retention(2);
+/// for i in 0..4 {
+///     stack.push(i);
+/// }
+/// while stack.pop().is_some() {}
+/// assert_eq!(stack.pool_len(), 2);
+/// stack.push(4); // No allocation.
+/// assert_eq!(stack.pool_len(), 1);
+/// assert_eq!(stack.peek(), Some(&4));
+/// ```
+pub struct PooledStack<E> {
+    // Nodes hold `Option<E>` so that the element can be moved out of a node without giving up
+    // the node's `Box`. Nodes in `head` always hold `Some`, nodes in `free` always hold `None`.
+    head: Option<Box<StackNode<Option<E>>>>,
+    size: usize,
+    free: Option<Box<StackNode<Option<E>>>>,
+    free_size: usize,
+    retention: usize,
+}
+
+impl<E> PooledStack<E> {
+    /// Default maximum number of free nodes to keep.
+    pub const DEFAULT_RETENTION: usize = 1024;
+
+    /// Create an empty stack, which keeps at most `DEFAULT_RETENTION` free nodes. Does not allocate.
+    pub fn new() -> Self {
+        Self::new_with_retention(Self::DEFAULT_RETENTION)
+    }
+
+    /// Create an empty stack, which keeps at most `retention` free nodes. Does not allocate.
+    pub fn new_with_retention(retention: usize) -> Self {
+        PooledStack{head: None, size: 0, free: None, free_size: 0, retention}
+    }
+
+    /// Push an element on top of the stack, reusing a free node, if there is one.
+    pub fn push(&mut self, elem: E) {
+        let mut new_node = match self.free.take() {
+            None => Box::new(StackNode{elem: Some(elem), next: None}),
+            Some(mut free_node) => {
+                self.free = free_node.next.take();
+                self.free_size -= 1;
+                free_node.elem = Some(elem);
+                free_node
+            }
+        };
+        new_node.next = self.head.take();
+        self.head = Some(new_node);
+        self.size += 1;
+    }
+
+    /// Remove the top element and return it, or `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<E> {
+        self.head.take().map(|mut old_head| {
+            self.head = old_head.next.take();
+            self.size -= 1;
+            let elem = old_head.elem.take().expect("node in use must hold an element");
+            self.recycle(old_head);
+            elem
+        })
+    }
+
+    /// The top element, if any, without removing it.
+    pub fn peek(&self) -> Option<&E> {
+        self.head.as_ref().and_then(|node| node.elem.as_ref())
+    }
+
+    /// Mutable reference to the top element, if any, without removing it.
+    pub fn peek_mut(&mut self) -> Option<&mut E> {
+        self.head.as_mut().and_then(|node| node.elem.as_mut())
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Number of free nodes available for reuse.
+    pub fn pool_len(&self) -> usize {
+        self.free_size
+    }
+
+    /// Change the maximum number of free nodes to keep, freeing any in excess of the new value.
+    pub fn set_retention(&mut self, retention: usize) {
+        self.retention = retention;
+        while self.free_size > retention {
+            let mut free_node = self.free.take().expect("free_size out of sync with free list");
+            self.free = free_node.next.take();
+            self.free_size -= 1;
+        }
+    }
+
+    /// Drop all elements, keeping their nodes for reuse up to the retention limit.
+    pub fn clear(&mut self) {
+        while let Some(mut old_head) = self.head.take() {
+            self.head = old_head.next.take();
+            old_head.elem = None;
+            self.recycle(old_head);
+        }
+        self.size = 0;
+    }
+
+    /// Put an unlinked, empty node on the free list, or free it if the list is full.
+    fn recycle(&mut self, mut node: Box<StackNode<Option<E>>>) {
+        if self.free_size < self.retention {
+            node.next = self.free.take();
+            self.free = Some(node);
+            self.free_size += 1;
+        }
+    }
+}
+
+impl<E> Drop for PooledStack<E> {
+    // Both chains need the same loop as in `Stack`.
+    fn drop(&mut self) {
+        for mut curr_head in [self.head.take(), self.free.take()] {
+            while let Some(boxed_node) = curr_head {
+                curr_head = boxed_node.next;
+            }
+        }
+    }
+}
+
+impl<E> Default for PooledStack<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pooled::PooledStack;
+
+    #[test]
+    fn int_test() {
+        let mut stack: PooledStack<i32> = PooledStack::new();
+        assert!(stack.pop().is_none());
+        assert_eq!(0, stack.len());
+        stack.push(1);
+        assert_eq!(1, stack.len());
+        assert_eq!(Some(&1), stack.peek());
+        *stack.peek_mut().unwrap() = 2;
+        assert_eq!(2, stack.pop().unwrap());
+        assert_eq!(0, stack.len());
+        assert!(stack.is_empty());
+        assert!(stack.pop().is_none());
+    }
+
+    #[test]
+    fn recycle_test() {
+        let mut stack: PooledStack<String> = PooledStack::new_with_retention(10);
+        for i in 0..20 {
+            stack.push(i.to_string());
+        }
+        assert_eq!(stack.pool_len(), 0);
+        for i in (10..20).rev() {
+            assert_eq!(stack.pop(), Some(i.to_string()));
+            assert_eq!(stack.pool_len(), 20 - i);
+        }
+        // Retention limit reached: popped nodes are freed.
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(), Some(i.to_string()));
+            assert_eq!(stack.pool_len(), 10);
+        }
+        for i in 0..15 {
+            stack.push(i.to_string());
+        }
+        assert_eq!(stack.pool_len(), 0);
+        assert_eq!(stack.len(), 15);
+        stack.clear();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pool_len(), 10);
+        stack.set_retention(3);
+        assert_eq!(stack.pool_len(), 3);
+        stack.set_retention(0);
+        stack.push("a".to_string());
+        stack.pop();
+        assert_eq!(stack.pool_len(), 0);
+    }
+
+    #[test]
+    fn order_test() {
+        let mut stack: PooledStack<i32> = PooledStack::new();
+        for round in 0..3 {
+            for i in 0..100 {
+                stack.push(round * 100 + i);
+            }
+            for i in (0..100).rev() {
+                assert_eq!(stack.pop(), Some(round * 100 + i));
+            }
+        }
+        assert!(stack.pop().is_none());
+        assert_eq!(stack.pool_len(), 100);
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut stack: PooledStack<i32> = PooledStack::new_with_retention(usize::MAX);
+        for i in 0..200000 {
+            stack.push(i);
+        }
+        for _ in 0..100000 {
+            stack.pop();
+        }
+        assert_eq!(stack.pool_len(), 100000);
+    }
+}
@@ -0,0 +1,368 @@
+use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
+use core::iter::Rev;
+use core::slice;
+use crate::stack::{self, Stack};
+
+/// Stack with small-buffer optimization: same API as `Stack`, but the bottom `N` elements are stored
+/// inline, in the `SmallStack` value itself, so a stack that never grows beyond `N` elements never
+/// allocates. Elements above the first `N` spill over into a `Stack` of heap-allocated nodes.
+///
+/// ```
+/// use rust_dust_stack::small::SmallStack;
+///
+/// let mut stack: SmallStack<i32, 4> = SmallStack::new();
+/// for i in 0..4 {
+///     stack.push(i);  // No allocation.
+/// }
+/// assert!(!stack.spilled());
+/// stack.push(4);
+/// assert!(stack.spilled());
+/// assert_eq!(stack.pop(), Some(4));
+/// assert_eq!(stack.peek(), Some(&3));
+/// ```
+pub struct SmallStack<E, const N: usize = 8> {
+    // Slots `0..inline_len` are `Some`, the rest are `None`. `spill` is only non-empty
+    // if all `N` slots are taken, so the top is the top of `spill`, if any, or the last inline slot.
+    inline: [Option<E>; N],
+    inline_len: usize,
+    spill: Stack<E>,
+}
+
+impl<E, const N: usize> SmallStack<E, N> {
+    /// Create an empty stack. Does not allocate.
+    pub fn new() -> Self {
+        SmallStack{inline: core::array::from_fn(|_| None), inline_len: 0, spill: Stack::new()}
+    }
+
+    /// Push an element on top of the stack. Only allocates if the inline slots are all taken.
+    pub fn push(&mut self, elem: E) {
+        if self.inline_len < N {
+            self.inline[self.inline_len] = Some(elem);
+            self.inline_len += 1;
+        } else {
+            self.spill.push(elem);
+        }
+    }
+
+    /// Remove the top element and return it, or `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<E> {
+        if !self.spill.is_empty() {
+            return self.spill.pop();
+        }
+        if self.inline_len == 0 {
+            return None;
+        }
+        self.inline_len -= 1;
+        self.inline[self.inline_len].take()
+    }
+
+    /// The top element, if any, without removing it.
+    pub fn peek(&self) -> Option<&E> {
+        match self.spill.peek() {
+            Some(elem) => Some(elem),
+            None => self.inline_len.checked_sub(1).and_then(|top| self.inline[top].as_ref()),
+        }
+    }
+
+    /// Mutable reference to the top element, if any, without removing it.
+    pub fn peek_mut(&mut self) -> Option<&mut E> {
+        if !self.spill.is_empty() {
+            return self.spill.peek_mut();
+        }
+        self.inline_len.checked_sub(1).and_then(|top| self.inline[top].as_mut())
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.inline_len + self.spill.len()
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `true` if the stack holds more than `N` elements, i.e. some of them are on the heap.
+    pub fn spilled(&self) -> bool {
+        !self.spill.is_empty()
+    }
+
+    /// Drop all elements, freeing the spilled nodes, if any.
+    pub fn clear(&mut self) {
+        self.spill.clear();
+        for slot in &mut self.inline[..self.inline_len] {
+            *slot = None;
+        }
+        self.inline_len = 0;
+    }
+
+    /// `true` if any of the elements equals `elem`. Linear time.
+    pub fn contains(&self, elem: &E) -> bool where E: PartialEq {
+        self.iter().any(|e| e == elem)
+    }
+
+    /// Iterate over the elements, top to bottom, without consuming them.
+    pub fn iter(&self) -> Iter<'_, E> {
+        Iter { spill: self.spill.iter(), inline: self.inline[..self.inline_len].iter().rev() }
+    }
+
+    /// Iterate over mutable references to the elements, top to bottom.
+    pub fn iter_mut(&mut self) -> IterMut<'_, E> {
+        IterMut { spill: self.spill.iter_mut(), inline: self.inline[..self.inline_len].iter_mut().rev() }
+    }
+}
+
+impl<E, const N: usize> Default for SmallStack<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Clone, const N: usize> Clone for SmallStack<E, N> {
+    fn clone(&self) -> Self {
+        SmallStack{inline: self.inline.clone(), inline_len: self.inline_len, spill: self.spill.clone()}
+    }
+}
+
+impl<E: Debug, const N: usize> Debug for SmallStack<E, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<E: PartialEq, const N: usize> PartialEq for SmallStack<E, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<E: Eq, const N: usize> Eq for SmallStack<E, N> {}
+
+impl<E: Hash, const N: usize> Hash for SmallStack<E, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for elem in self.iter() {
+            elem.hash(state);
+        }
+    }
+}
+
+/// Elements are pushed in iteration order, so the last one ends up on top.
+impl<E, const N: usize> FromIterator<E> for SmallStack<E, N> {
+    fn from_iter<I: IntoIterator<Item=E>>(iter: I) -> Self {
+        let mut stack = SmallStack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+impl<E, const N: usize> Extend<E> for SmallStack<E, N> {
+    fn extend<I: IntoIterator<Item=E>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+/// Consuming iterator, returned by `SmallStack::into_iter()`. Pops the elements off the stack.
+pub struct IntoIter<E, const N: usize>(SmallStack<E, N>);
+
+impl<E, const N: usize> Iterator for IntoIter<E, N> {
+    type Item = E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+}
+
+/// Borrowing iterator, returned by `SmallStack::iter()`. Walks the spilled elements first,
+/// then the inline ones in reverse order.
+pub struct Iter<'a, E> {
+    spill: stack::Iter<'a, E>,
+    inline: Rev<slice::Iter<'a, Option<E>>>,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.spill.next().or_else(|| self.inline.next().and_then(Option::as_ref))
+    }
+}
+
+/// Mutably borrowing iterator, returned by `SmallStack::iter_mut()`.
+pub struct IterMut<'a, E> {
+    spill: stack::IterMut<'a, E>,
+    inline: Rev<slice::IterMut<'a, Option<E>>>,
+}
+
+impl<'a, E> Iterator for IterMut<'a, E> {
+    type Item = &'a mut E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.spill.next().or_else(|| self.inline.next().and_then(Option::as_mut))
+    }
+}
+
+impl<E, const N: usize> IntoIterator for SmallStack<E, N> {
+    type Item = E;
+    type IntoIter = IntoIter<E, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, E, const N: usize> IntoIterator for &'a SmallStack<E, N> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, E, const N: usize> IntoIterator for &'a mut SmallStack<E, N> {
+    type Item = &'a mut E;
+    type IntoIter = IterMut<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Same tests as `Stack`'s, plus spill boundary tests.
+#[cfg(test)]
+mod tests {
+    use crate::small::SmallStack;
+
+    #[test]
+    fn int_test() {
+        let mut stack: SmallStack<i32> = SmallStack::new();
+        assert!(stack.pop().is_none());
+        assert_eq!(0, stack.len());
+        stack.push(1);
+        assert_eq!(1, stack.len());
+        assert_eq!(1, stack.pop().unwrap());
+        assert_eq!(0, stack.len());
+        assert!(stack.pop().is_none());
+    }
+
+    #[test]
+    fn struct_test() {
+        #[derive(Debug)]
+        struct Struct(i32, String);
+        impl PartialEq for Struct {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0 && self.1 == other.1
+            }
+        }
+        let mut stack: SmallStack<Struct, 4> = SmallStack::new();
+        for i in 1..10 {
+            stack.push(Struct(i, i.to_string()));
+            assert_eq!(i, stack.len() as i32);
+        }
+
+        for i in (1..10).rev() {
+            assert_eq!(Struct(i, i.to_string()), stack.pop().unwrap());
+            assert_eq!(i-1, stack.len() as i32);
+        }
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut stack: SmallStack<i32> = SmallStack::new();
+        for i in 0..100000 {
+            stack.push(i);
+        }
+    }
+
+    #[test]
+    fn iter_test() {
+        let mut stack: SmallStack<i32, 3> = SmallStack::new();
+        assert_eq!(stack.iter().next(), None);
+        for i in 0..10 {
+            stack.push(i);
+        }
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), (0..10).rev().collect::<Vec<_>>());
+        for elem in &mut stack {
+            *elem *= 10;
+        }
+        assert_eq!(stack.len(), 10);
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), (0..10).rev().map(|i| i * 10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn peek_test() {
+        let mut stack: SmallStack<i32, 1> = SmallStack::new();
+        assert_eq!(stack.peek(), None);
+        assert_eq!(stack.peek_mut(), None);
+        stack.push(1);
+        assert_eq!(stack.peek(), Some(&1));
+        stack.push(2);
+        assert_eq!(stack.peek(), Some(&2));
+        *stack.peek_mut().unwrap() = 20;
+        assert_eq!(stack.pop(), Some(20));
+        *stack.peek_mut().unwrap() = 10;
+        assert_eq!(stack.peek(), Some(&10));
+    }
+
+    #[test]
+    fn spill_test() {
+        // Push and pop back and forth across the inline boundary.
+        let mut stack: SmallStack<String, 3> = SmallStack::new();
+        for round in 1..8 {
+            for i in 0..round {
+                stack.push(i.to_string());
+                assert_eq!(stack.spilled(), i >= 3);
+            }
+            assert_eq!(stack.len(), round);
+            assert_eq!(stack.peek(), Some(&(round - 1).to_string()));
+            for i in (0..round).rev() {
+                assert_eq!(stack.pop(), Some(i.to_string()));
+            }
+            assert!(stack.is_empty());
+            assert!(!stack.spilled());
+        }
+        stack.extend((0..5).map(|i| i.to_string()));
+        stack.clear();
+        assert!(stack.is_empty());
+        assert!(!stack.spilled());
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn zero_inline_test() {
+        // With no inline slots, every element spills.
+        let mut stack: SmallStack<i32, 0> = SmallStack::new();
+        assert!(stack.is_empty());
+        stack.push(1);
+        stack.push(2);
+        assert!(!stack.is_empty());
+        assert!(stack.spilled());
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.peek(), Some(&2));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn clone_eq_test() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        fn hash(stack: &SmallStack<String, 4>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            stack.hash(&mut hasher);
+            hasher.finish()
+        }
+        let stack: SmallStack<String, 4> = (0..10).map(|i| i.to_string()).collect();
+        let mut clone = stack.clone();
+        assert_eq!(clone, stack);
+        assert_eq!(hash(&clone), hash(&stack));
+        assert!(clone.contains(&"0".to_string()));
+        assert!(!clone.contains(&"10".to_string()));
+        clone.pop();
+        assert_ne!(clone, stack);
+        clone.push("0".to_string());
+        assert_ne!(clone, stack);
+        assert_ne!(hash(&clone), hash(&stack));
+        assert_eq!(format!("{:?}", SmallStack::<i32, 2>::from_iter(1..4)), "[3, 2, 1]");
+    }
+}
@@ -1,24 +1,45 @@
-struct Stack<E> {
+use alloc::boxed::Box;
+use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
+
+/// Singly-linked LIFO stack of heap-allocated nodes.
+///
+/// ```
+/// use rust_dust_stack::stack::Stack;
+///
+/// let mut stack = Stack::new();
+/// stack.push("a");
+/// stack.push("b");
+/// assert_eq!(stack.len(), 2);
+/// assert_eq!(stack.pop(), Some("b"));
+/// assert_eq!(stack.pop(), Some("a"));
+/// assert_eq!(stack.pop(), None);
+/// ```
+pub struct Stack<E> {
     head: Option<Box<StackNode<E>>>,
     size: usize,
 }
 
-struct StackNode<E> {
-    elem: E,
-    next: Option<Box<StackNode<E>>>,
+pub(crate) struct StackNode<E> {
+    pub(crate) elem: E,
+    pub(crate) next: Option<Box<StackNode<E>>>,
 }
 
 impl<E> Stack<E> {
-    fn new() -> Self {
+    /// Create an empty stack. Does not allocate.
+    pub fn new() -> Self {
         Stack{head: None, size: 0}
     }
-    fn push(&mut self, elem: E) {
+
+    /// Push an element on top of the stack.
+    pub fn push(&mut self, elem: E) {
         let new_node = Box::new(StackNode{elem, next: self.head.take()});
         self.head = Some(new_node);
         self.size += 1;
     }
 
-    fn pop(&mut self) -> Option<E> {
+    /// Remove the top element and return it, or `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<E> {
         match self.head.take() {
             None => None,
             Some(old_head) => {
@@ -28,8 +49,253 @@ impl<E> Stack<E> {
             }
         }
     }
+
+    /// The top element, if any, without removing it.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = Stack::new();
+    /// assert_eq!(stack.peek(), None);
+    /// stack.push(1);
+    /// assert_eq!(stack.peek(), Some(&1));
+    /// assert_eq!(stack.len(), 1);
+    /// ```
+    pub fn peek(&self) -> Option<&E> {
+        self.head.as_ref().map(|node| &node.elem)
+    }
+
+    /// Mutable reference to the top element, if any, without removing it.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push(1);
+    /// if let Some(top) = stack.peek_mut() {
+    ///     *top = 2;
+    /// }
+    /// assert_eq!(stack.pop(), Some(2));
+    /// ```
+    pub fn peek_mut(&mut self) -> Option<&mut E> {
+        self.head.as_mut().map(|node| &mut node.elem)
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Drop all elements. Same loop as in `drop()`, so that large stacks don't blow the call stack.
+    pub fn clear(&mut self) {
+        let mut curr_head = self.head.take();
+        while let Some(boxed_node) = curr_head {
+            curr_head = boxed_node.next;
+        }
+        self.size = 0;
+    }
+
+    /// `true` if any of the elements equals `elem`. Linear time.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let stack: Stack<i32> = (0..10).collect();
+    /// assert!(stack.contains(&5));
+    /// assert!(!stack.contains(&10));
+    /// ```
+    pub fn contains(&self, elem: &E) -> bool where E: PartialEq {
+        self.iter().any(|e| e == elem)
+    }
+
+    /// Iterate over the elements, top to bottom, without consuming them.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let stack: Stack<i32> = (1..4).collect();
+    /// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+    /// assert_eq!(stack.len(), 3);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, E> {
+        Iter { next: self.head.as_deref() }
+    }
+
+    /// Iterate over mutable references to the elements, top to bottom.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = (1..4).collect();
+    /// for elem in stack.iter_mut() {
+    ///     *elem *= 10;
+    /// }
+    /// assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![30, 20, 10]);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, E> {
+        IterMut { next: self.head.as_deref_mut() }
+    }
+
+    /// Move all elements of `other` on top of this stack, in their current order, leaving `other`
+    /// empty. Relinks `other`'s bottom node to this stack's top node, so it takes O(`other.len()`).
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = (0..3).collect();
+    /// let mut other: Stack<i32> = (3..6).collect();
+    /// stack.append(&mut other);
+    /// assert!(other.is_empty());
+    /// assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![5, 4, 3, 2, 1, 0]);
+    /// ```
+    pub fn append(&mut self, other: &mut Stack<E>) {
+        let mut cursor = &mut other.head;
+        while let Some(node) = cursor {
+            cursor = &mut node.next;
+        }
+        *cursor = self.head.take();
+        self.head = other.head.take();
+        self.size += other.size;
+        other.size = 0;
+    }
+
+    /// Split the stack in two: this stack keeps the top `at` elements, and the rest are returned
+    /// in a new stack. Takes O(`at`). Panics if `at > len`.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = (0..5).collect();
+    /// let bottom = stack.split_off(2);
+    /// assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![4, 3]);
+    /// assert_eq!(bottom.into_iter().collect::<Vec<_>>(), vec![2, 1, 0]);
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> Stack<E> {
+        assert!(at <= self.size, "split index {} out of bounds for stack of size {}", at, self.size);
+        let mut cursor = &mut self.head;
+        for _ in 0..at {
+            cursor = &mut cursor.as_mut().unwrap().next;
+        }
+        let bottom = Stack{head: cursor.take(), size: self.size - at};
+        self.size = at;
+        bottom
+    }
+
+    /// Reverse the order of the elements in place, so that the bottom element ends up on top.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = (0..3).collect();
+    /// stack.reverse();
+    /// assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    /// ```
+    pub fn reverse(&mut self) {
+        let mut rest = self.head.take();
+        while let Some(mut node) = rest {
+            rest = node.next.take();
+            node.next = self.head.take();
+            self.head = Some(node);
+        }
+    }
+
+    /// Keep only the elements for which `f` returns `true`, in their current order, dropping the rest.
+    /// `f` is called once for each element, top to bottom.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = (0..6).collect();
+    /// stack.retain(|e| e % 2 == 0);
+    /// assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![4, 2, 0]);
+    /// ```
+    pub fn retain<F: FnMut(&E) -> bool>(&mut self, mut f: F) {
+        let rest = self.head.take();
+        let mut guard = RetainGuard { stack: self, kept: None, rest };
+        while let Some(node) = &guard.rest {
+            // Call `f` while the node is still in `rest`, so that if it panics, the guard relinks it.
+            let keep = f(&node.elem);
+            let mut node = guard.rest.take().unwrap();
+            guard.rest = node.next.take();
+            if keep {
+                node.next = guard.kept.take();
+                guard.kept = Some(node);
+            } else {
+                guard.stack.size -= 1;
+            }
+        }
+    }
+
+    /// Iterator that pops the elements, top to bottom. Unlike `into_iter()`, does not consume the stack,
+    /// and any elements the iterator did not get to are dropped with it, leaving the stack empty.
+    /// ```
+    /// use rust_dust_stack::stack::Stack;
+    ///
+    /// let mut stack: Stack<i32> = (0..5).collect();
+    /// assert_eq!(stack.drain().take(2).collect::<Vec<_>>(), vec![4, 3]);
+    /// assert!(stack.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, E> {
+        Drain { stack: self }
+    }
 }
 
+impl<E> Default for Stack<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Clone> Clone for Stack<E> {
+    /// Copies the nodes top to bottom, appending each new node at the tail of the copy,
+    /// so that the order is preserved without recursion or an intermediate buffer.
+    fn clone(&self) -> Self {
+        let mut result = Stack::new();
+        let mut tail = &mut result.head;
+        for elem in self.iter() {
+            let new_node = tail.insert(Box::new(StackNode{elem: elem.clone(), next: None}));
+            tail = &mut new_node.next;
+        }
+        result.size = self.size;
+        result
+    }
+}
+
+impl<E: Debug> Debug for Stack<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<E: PartialEq> PartialEq for Stack<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<E: Eq> Eq for Stack<E> {}
+
+impl<E: Hash> Hash for Stack<E> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size);
+        for elem in self.iter() {
+            elem.hash(state);
+        }
+    }
+}
+
+/// Elements are pushed in iteration order, so the last one ends up on top.
+impl<E> FromIterator<E> for Stack<E> {
+    fn from_iter<I: IntoIterator<Item=E>>(iter: I) -> Self {
+        let mut stack = Stack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+impl<E> Extend<E> for Stack<E> {
+    fn extend<I: IntoIterator<Item=E>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
 
 impl <E> Drop for Stack<E> {
 
@@ -40,17 +306,111 @@ impl <E> Drop for Stack<E> {
 
     // This is better
     fn drop(&mut self) {
-        let mut curr_head = self.head.take();
-        while let Some(boxed_node) = curr_head {
-            curr_head = boxed_node.next;
-        }
+        self.clear();
     }
 }
 
-impl <E> Iterator for Stack<E> {
+/// Consuming iterator, returned by `Stack::into_iter()`. Pops the elements off the stack.
+pub struct IntoIter<E>(Stack<E>);
+
+impl <E> Iterator for IntoIter<E> {
     type Item = E;
     fn next(&mut self) -> Option<Self::Item> {
-        self.pop()
+        self.0.pop()
+    }
+}
+
+/// Borrowing iterator, returned by `Stack::iter()`.
+pub struct Iter<'a, E> {
+    next: Option<&'a StackNode<E>>,
+}
+
+impl<'a, E> Iterator for Iter<'a, E> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            &node.elem
+        })
+    }
+}
+
+/// Mutably borrowing iterator, returned by `Stack::iter_mut()`.
+pub struct IterMut<'a, E> {
+    next: Option<&'a mut StackNode<E>>,
+}
+
+impl<'a, E> Iterator for IterMut<'a, E> {
+    type Item = &'a mut E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.take().map(|node| {
+            self.next = node.next.as_deref_mut();
+            &mut node.elem
+        })
+    }
+}
+
+impl<E> IntoIterator for Stack<E> {
+    type Item = E;
+    type IntoIter = IntoIter<E>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, E> IntoIterator for &'a Stack<E> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, E> IntoIterator for &'a mut Stack<E> {
+    type Item = &'a mut E;
+    type IntoIter = IterMut<'a, E>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Draining iterator, returned by `Stack::drain()`.
+pub struct Drain<'a, E> {
+    stack: &'a mut Stack<E>,
+}
+
+impl<E> Iterator for Drain<'_, E> {
+    type Item = E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.stack.size, Some(self.stack.size))
+    }
+}
+
+impl<E> Drop for Drain<'_, E> {
+    fn drop(&mut self) {
+        self.stack.clear();
+    }
+}
+
+/// Relinks the stack when `Stack::retain()` returns or unwinds: the kept nodes, which are held
+/// in reverse order, are pushed back onto the chain of nodes that `f` did not get to.
+struct RetainGuard<'a, E> {
+    stack: &'a mut Stack<E>,
+    kept: Option<Box<StackNode<E>>>,
+    rest: Option<Box<StackNode<E>>>,
+}
+
+impl<E> Drop for RetainGuard<'_, E> {
+    fn drop(&mut self) {
+        while let Some(mut node) = self.kept.take() {
+            self.kept = node.next.take();
+            node.next = self.rest.take();
+            self.rest = Some(node);
+        }
+        self.stack.head = self.rest.take();
     }
 }
 
@@ -78,10 +438,6 @@ mod tests {
             fn eq(&self, other: &Self) -> bool {
                 self.0 == other.0 && self.1 == other.1
             }
-
-            fn ne(&self, other: &Self) -> bool {
-                !self.eq(other)
-            }
         }
         let mut stack: Stack<Struct> = Stack::new();
         for i in 1..10 {
@@ -89,7 +445,7 @@ mod tests {
             assert_eq!(i, stack.size as i32);
         }
 
-        for i in 10..1 {
+        for i in (1..10).rev() {
             assert_eq!(Struct(i, i.to_string()), stack.pop().unwrap());
             assert_eq!(i-1, stack.size as i32);
         }
@@ -114,4 +470,226 @@ mod tests {
             println!("{}", i);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn borrow_iter_test() {
+        let mut stack: Stack<i32> = Stack::new();
+        assert_eq!(stack.iter().next(), None);
+        for i in 0..10 {
+            stack.push(i);
+        }
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), (0..10).rev().collect::<Vec<_>>());
+        // Still all there.
+        assert_eq!(stack.size, 10);
+        let mut count = 0;
+        for _ in &stack {
+            count += 1;
+        }
+        assert_eq!(count, 10);
+        assert_eq!(stack.pop(), Some(9));
+    }
+
+    #[test]
+    fn borrow_iter_mut_test() {
+        let mut stack: Stack<i32> = Stack::new();
+        for i in 0..10 {
+            stack.push(i);
+        }
+        for elem in stack.iter_mut() {
+            *elem *= 10;
+        }
+        for elem in &mut stack {
+            *elem += 1;
+        }
+        assert_eq!(stack.size, 10);
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(), Some(i * 10 + 1));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn peek_test() {
+        let mut stack: Stack<i32> = Stack::new();
+        assert_eq!(stack.peek(), None);
+        assert_eq!(stack.peek_mut(), None);
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.peek(), Some(&2));
+        *stack.peek_mut().unwrap() = 20;
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(20));
+        assert_eq!(stack.peek(), Some(&1));
+    }
+
+    #[test]
+    fn len_clear_test() {
+        let mut stack: Stack<i32> = Stack::default();
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
+        for i in 0..100000 {
+            stack.push(i);
+        }
+        assert!(!stack.is_empty());
+        assert_eq!(stack.len(), 100000);
+        stack.clear();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        stack.push(1);
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn collect_extend_test() {
+        let mut stack: Stack<i32> = (0..5).collect();
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.peek(), Some(&4));
+        stack.extend(vec![5, 6]);
+        assert_eq!(stack.len(), 7);
+        assert!(stack.contains(&0));
+        assert!(stack.contains(&6));
+        assert!(!stack.contains(&7));
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn clone_eq_test() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        fn hash(stack: &Stack<String>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            stack.hash(&mut hasher);
+            hasher.finish()
+        }
+        let stack: Stack<String> = (0..100000).map(|i| i.to_string()).collect();
+        let mut clone = stack.clone();
+        assert_eq!(clone.len(), stack.len());
+        assert_eq!(clone, stack);
+        assert_eq!(hash(&clone), hash(&stack));
+        clone.pop();
+        assert_ne!(clone, stack);
+        clone.push("0".to_string());
+        assert_ne!(clone, stack);
+        assert_ne!(hash(&clone), hash(&stack));
+        assert_eq!(Stack::<String>::new(), Stack::<String>::default());
+    }
+
+    #[test]
+    fn debug_test() {
+        let stack: Stack<i32> = (1..4).collect();
+        assert_eq!(format!("{:?}", stack), "[3, 2, 1]");
+        assert_eq!(format!("{:?}", Stack::<i32>::new()), "[]");
+    }
+
+    #[test]
+    fn append_test() {
+        let mut stack: Stack<i32> = (0..3).collect();
+        let mut empty: Stack<i32> = Stack::new();
+        stack.append(&mut empty);
+        assert_eq!(stack.len(), 3);
+        empty.append(&mut stack);
+        assert_eq!(empty.len(), 3);
+        assert!(stack.is_empty());
+        let mut other: Stack<i32> = (3..100000).collect();
+        empty.append(&mut other);
+        assert_eq!(empty.len(), 100000);
+        assert_eq!(other.len(), 0);
+        assert_eq!(other.pop(), None);
+        assert!(empty.into_iter().eq((0..100000).rev()));
+    }
+
+    #[test]
+    fn split_off_test() {
+        let mut stack: Stack<i32> = (0..10).collect();
+        let bottom = stack.split_off(10);
+        assert!(bottom.is_empty());
+        assert_eq!(stack.len(), 10);
+        let mut bottom = stack.split_off(3);
+        assert_eq!(stack.len(), 3);
+        assert_eq!(bottom.len(), 7);
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec![9, 8, 7]);
+        assert_eq!(bottom.iter().copied().collect::<Vec<_>>(), vec![6, 5, 4, 3, 2, 1, 0]);
+        let all = bottom.split_off(0);
+        assert!(bottom.is_empty());
+        assert_eq!(all.len(), 7);
+        // Split and append are inverse.
+        stack.append(&mut bottom);
+        let mut all = all;
+        all.append(&mut stack);
+        assert_eq!(all, (0..10).collect());
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_panic_test() {
+        let mut stack: Stack<i32> = (0..10).collect();
+        stack.split_off(11);
+    }
+
+    #[test]
+    fn reverse_test() {
+        let mut stack: Stack<i32> = Stack::new();
+        stack.reverse();
+        assert!(stack.is_empty());
+        stack.push(1);
+        stack.reverse();
+        assert_eq!(stack.peek(), Some(&1));
+        let mut stack: Stack<i32> = (0..100000).collect();
+        stack.reverse();
+        assert_eq!(stack.len(), 100000);
+        assert!(stack.iter().copied().eq(0..100000));
+    }
+
+    #[test]
+    fn retain_test() {
+        let mut stack: Stack<i32> = (0..10).collect();
+        let mut visited = Vec::new();
+        stack.retain(|e| {
+            visited.push(*e);
+            e % 3 == 0
+        });
+        assert_eq!(visited, (0..10).rev().collect::<Vec<_>>());
+        assert_eq!(stack.len(), 4);
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec![9, 6, 3, 0]);
+        stack.retain(|_| true);
+        assert_eq!(stack.len(), 4);
+        stack.retain(|_| false);
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn retain_panic_test() {
+        use std::panic::{self, AssertUnwindSafe};
+        let mut stack: Stack<i32> = (0..200000).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            stack.retain(|e| {
+                assert!(*e != 100000, "retain predicate panicked");
+                e % 2 == 0
+            });
+        }));
+        assert!(result.is_err());
+        // Visited elements were filtered, the rest are untouched.
+        assert_eq!(stack.len(), 49999 + 100001);
+        assert!(stack.iter().copied().eq((100001..200000).rev().filter(|e| e % 2 == 0).chain((0..=100000).rev())));
+        drop(stack);
+    }
+
+    #[test]
+    fn drain_test() {
+        let mut stack: Stack<String> = (0..10).map(|i| i.to_string()).collect();
+        let drain = stack.drain();
+        assert_eq!(drain.size_hint(), (10, Some(10)));
+        assert_eq!(drain.collect::<Vec<_>>().len(), 10);
+        assert!(stack.is_empty());
+        stack.extend((0..10).map(|i| i.to_string()));
+        for s in stack.drain() {
+            if s == "5" {
+                break;
+            }
+        }
+        assert!(stack.is_empty());
+        stack.push("a".to_string());
+        assert_eq!(stack.len(), 1);
+    }
+}
@@ -0,0 +1,452 @@
+use alloc::boxed::Box;
+use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
+use core::iter::Rev;
+use core::slice;
+
+/// Unrolled linked stack: same API as `Stack`, but each node holds up to `N` elements in an array.
+/// Like `Stack`, it never reallocates, so every push is O(1) in the worst case. Unlike `Stack`,
+/// it allocates once per `N` pushes, and neighbouring elements are mostly adjacent in memory.
+///
+/// ```
+/// use rust_dust_stack::unrolled::UnrolledStack;
+///
+/// let mut stack: UnrolledStack<i32, 4> = UnrolledStack::new();
+/// for i in 0..10 {
+///     stack.push(i);  // Allocates 3 nodes.
+/// }
+/// assert_eq!(stack.len(), 10);
+/// assert_eq!(stack.pop(), Some(9));
+/// assert_eq!(stack.peek(), Some(&8));
+/// ```
+pub struct UnrolledStack<E, const N: usize = 32> {
+    head: Option<Box<UnrolledNode<E, N>>>,
+    size: usize,
+}
+
+struct UnrolledNode<E, const N: usize> {
+    // Slots `0..len` are `Some`, the rest are `None`. The element at `len - 1` is the top.
+    // Every node has at least one element, and every node but the head is full.
+    elems: [Option<E>; N],
+    len: usize,
+    next: Option<Box<UnrolledNode<E, N>>>,
+}
+
+impl<E, const N: usize> UnrolledStack<E, N> {
+    /// Create an empty stack. Does not allocate.
+    pub fn new() -> Self {
+        const { assert!(N > 0, "node capacity must be positive") };
+        UnrolledStack{head: None, size: 0}
+    }
+
+    /// Push an element on top of the stack. Allocates a new node if the head node is full.
+    pub fn push(&mut self, elem: E) {
+        match &mut self.head {
+            Some(head) if head.len < N => {
+                head.elems[head.len] = Some(elem);
+                head.len += 1;
+            }
+            _ => {
+                let mut elems = core::array::from_fn(|_| None);
+                elems[0] = Some(elem);
+                let new_node = Box::new(UnrolledNode{elems, len: 1, next: self.head.take()});
+                self.head = Some(new_node);
+            }
+        }
+        self.size += 1;
+    }
+
+    /// Remove the top element and return it, or `None` if the stack is empty.
+    /// Frees the head node if it was its last element.
+    pub fn pop(&mut self) -> Option<E> {
+        let head = self.head.as_mut()?;
+        head.len -= 1;
+        let elem = head.elems[head.len].take();
+        if head.len == 0 {
+            let old_head = self.head.take().unwrap();
+            self.head = old_head.next;
+        }
+        self.size -= 1;
+        elem
+    }
+
+    /// The top element, if any, without removing it.
+    pub fn peek(&self) -> Option<&E> {
+        self.head.as_ref().and_then(|node| node.elems[node.len - 1].as_ref())
+    }
+
+    /// Mutable reference to the top element, if any, without removing it.
+    pub fn peek_mut(&mut self) -> Option<&mut E> {
+        self.head.as_mut().and_then(|node| node.elems[node.len - 1].as_mut())
+    }
+
+    /// Number of elements in the stack.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// `true` if the stack has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Drop all elements, without recursion.
+    pub fn clear(&mut self) {
+        let mut curr_head = self.head.take();
+        while let Some(boxed_node) = curr_head {
+            curr_head = boxed_node.next;
+        }
+        self.size = 0;
+    }
+
+    /// `true` if any of the elements equals `elem`. Linear time.
+    pub fn contains(&self, elem: &E) -> bool where E: PartialEq {
+        self.iter().any(|e| e == elem)
+    }
+
+    /// Iterate over the elements, top to bottom, without consuming them.
+    pub fn iter(&self) -> Iter<'_, E, N> {
+        Iter { elems: [].iter().rev(), next: self.head.as_deref() }
+    }
+
+    /// Iterate over mutable references to the elements, top to bottom.
+    pub fn iter_mut(&mut self) -> IterMut<'_, E, N> {
+        IterMut { elems: [].iter_mut().rev(), next: self.head.as_deref_mut() }
+    }
+}
+
+impl<E, const N: usize> Drop for UnrolledStack<E, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<E, const N: usize> Default for UnrolledStack<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Clone, const N: usize> Clone for UnrolledStack<E, N> {
+    /// Copies the nodes top to bottom, appending each new node at the tail of the copy.
+    fn clone(&self) -> Self {
+        let mut result = UnrolledStack::new();
+        let mut tail = &mut result.head;
+        let mut curr = self.head.as_deref();
+        while let Some(node) = curr {
+            let new_node = tail.insert(Box::new(UnrolledNode{elems: node.elems.clone(), len: node.len, next: None}));
+            tail = &mut new_node.next;
+            curr = node.next.as_deref();
+        }
+        result.size = self.size;
+        result
+    }
+}
+
+impl<E: Debug, const N: usize> Debug for UnrolledStack<E, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<E: PartialEq, const N: usize> PartialEq for UnrolledStack<E, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<E: Eq, const N: usize> Eq for UnrolledStack<E, N> {}
+
+impl<E: Hash, const N: usize> Hash for UnrolledStack<E, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size);
+        for elem in self.iter() {
+            elem.hash(state);
+        }
+    }
+}
+
+/// Elements are pushed in iteration order, so the last one ends up on top.
+impl<E, const N: usize> FromIterator<E> for UnrolledStack<E, N> {
+    fn from_iter<I: IntoIterator<Item=E>>(iter: I) -> Self {
+        let mut stack = UnrolledStack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+impl<E, const N: usize> Extend<E> for UnrolledStack<E, N> {
+    fn extend<I: IntoIterator<Item=E>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+/// Consuming iterator, returned by `UnrolledStack::into_iter()`. Pops the elements off the stack.
+pub struct IntoIter<E, const N: usize>(UnrolledStack<E, N>);
+
+impl<E, const N: usize> Iterator for IntoIter<E, N> {
+    type Item = E;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+}
+
+/// Borrowing iterator, returned by `UnrolledStack::iter()`. Walks the current node's elements
+/// in reverse order, then moves on to the next node.
+pub struct Iter<'a, E, const N: usize> {
+    elems: Rev<slice::Iter<'a, Option<E>>>,
+    next: Option<&'a UnrolledNode<E, N>>,
+}
+
+impl<'a, E, const N: usize> Iterator for Iter<'a, E, N> {
+    type Item = &'a E;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(elem) = self.elems.next() {
+                return elem.as_ref();
+            }
+            let node = self.next?;
+            self.elems = node.elems[..node.len].iter().rev();
+            self.next = node.next.as_deref();
+        }
+    }
+}
+
+/// Mutably borrowing iterator, returned by `UnrolledStack::iter_mut()`.
+pub struct IterMut<'a, E, const N: usize> {
+    elems: Rev<slice::IterMut<'a, Option<E>>>,
+    next: Option<&'a mut UnrolledNode<E, N>>,
+}
+
+impl<'a, E, const N: usize> Iterator for IterMut<'a, E, N> {
+    type Item = &'a mut E;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(elem) = self.elems.next() {
+                return elem.as_mut();
+            }
+            // Split the node borrow, so that its elements and its link can be borrowed separately.
+            let UnrolledNode{elems, len, next} = self.next.take()?;
+            self.elems = elems[..*len].iter_mut().rev();
+            self.next = next.as_deref_mut();
+        }
+    }
+}
+
+impl<E, const N: usize> IntoIterator for UnrolledStack<E, N> {
+    type Item = E;
+    type IntoIter = IntoIter<E, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, E, const N: usize> IntoIterator for &'a UnrolledStack<E, N> {
+    type Item = &'a E;
+    type IntoIter = Iter<'a, E, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, E, const N: usize> IntoIterator for &'a mut UnrolledStack<E, N> {
+    type Item = &'a mut E;
+    type IntoIter = IterMut<'a, E, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Same tests as `Stack`'s, plus node boundary tests.
+#[cfg(test)]
+mod tests {
+    use crate::unrolled::UnrolledStack;
+
+    #[test]
+    fn int_test() {
+        let mut stack: UnrolledStack<i32> = UnrolledStack::new();
+        assert!(stack.pop().is_none());
+        assert_eq!(0, stack.len());
+        stack.push(1);
+        assert_eq!(1, stack.len());
+        assert_eq!(1, stack.pop().unwrap());
+        assert_eq!(0, stack.len());
+        assert!(stack.pop().is_none());
+    }
+
+    #[test]
+    fn struct_test() {
+        #[derive(Debug)]
+        struct Struct(i32, String);
+        impl PartialEq for Struct {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0 && self.1 == other.1
+            }
+        }
+        let mut stack: UnrolledStack<Struct, 4> = UnrolledStack::new();
+        for i in 1..10 {
+            stack.push(Struct(i, i.to_string()));
+            assert_eq!(i, stack.len() as i32);
+        }
+
+        for i in (1..10).rev() {
+            assert_eq!(Struct(i, i.to_string()), stack.pop().unwrap());
+            assert_eq!(i-1, stack.len() as i32);
+        }
+    }
+
+    #[test]
+    fn drop_test() {
+        let mut stack: UnrolledStack<i32> = UnrolledStack::new();
+        for i in 0..100000 {
+            stack.push(i);
+        }
+        // Worst case for the node count.
+        let mut stack: UnrolledStack<i32, 1> = UnrolledStack::new();
+        for i in 0..100000 {
+            stack.push(i);
+        }
+    }
+
+    #[test]
+    fn iter_test() {
+        let mut stack: UnrolledStack<String> = UnrolledStack::new();
+        for i in 0..100 {
+            stack.push(i.to_string());
+        }
+
+        // Consumes the stack, top to bottom.
+        let elems = stack.into_iter().collect::<Vec<_>>();
+        assert_eq!(elems, (0..100).rev().map(|i| i.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn borrow_iter_test() {
+        let mut stack: UnrolledStack<i32, 3> = UnrolledStack::new();
+        assert_eq!(stack.iter().next(), None);
+        for i in 0..10 {
+            stack.push(i);
+        }
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), (0..10).rev().collect::<Vec<_>>());
+        // Still all there.
+        assert_eq!(stack.len(), 10);
+        let mut count = 0;
+        for _ in &stack {
+            count += 1;
+        }
+        assert_eq!(count, 10);
+        assert_eq!(stack.pop(), Some(9));
+    }
+
+    #[test]
+    fn borrow_iter_mut_test() {
+        let mut stack: UnrolledStack<i32, 3> = UnrolledStack::new();
+        for i in 0..10 {
+            stack.push(i);
+        }
+        for elem in stack.iter_mut() {
+            *elem *= 10;
+        }
+        for elem in &mut stack {
+            *elem += 1;
+        }
+        assert_eq!(stack.len(), 10);
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(), Some(i * 10 + 1));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn peek_test() {
+        let mut stack: UnrolledStack<i32, 1> = UnrolledStack::new();
+        assert_eq!(stack.peek(), None);
+        assert_eq!(stack.peek_mut(), None);
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.peek(), Some(&2));
+        *stack.peek_mut().unwrap() = 20;
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(20));
+        assert_eq!(stack.peek(), Some(&1));
+    }
+
+    #[test]
+    fn len_clear_test() {
+        let mut stack: UnrolledStack<i32> = UnrolledStack::default();
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
+        for i in 0..100000 {
+            stack.push(i);
+        }
+        assert!(!stack.is_empty());
+        assert_eq!(stack.len(), 100000);
+        stack.clear();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        stack.push(1);
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn collect_extend_test() {
+        let mut stack: UnrolledStack<i32, 2> = (0..5).collect();
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.peek(), Some(&4));
+        stack.extend(vec![5, 6]);
+        assert_eq!(stack.len(), 7);
+        assert!(stack.contains(&0));
+        assert!(stack.contains(&6));
+        assert!(!stack.contains(&7));
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn clone_eq_test() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        fn hash(stack: &UnrolledStack<String>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            stack.hash(&mut hasher);
+            hasher.finish()
+        }
+        let stack: UnrolledStack<String> = (0..100000).map(|i| i.to_string()).collect();
+        let mut clone = stack.clone();
+        assert_eq!(clone.len(), stack.len());
+        assert_eq!(clone, stack);
+        assert_eq!(hash(&clone), hash(&stack));
+        clone.pop();
+        assert_ne!(clone, stack);
+        clone.push("0".to_string());
+        assert_ne!(clone, stack);
+        assert_ne!(hash(&clone), hash(&stack));
+        assert_eq!(UnrolledStack::<String>::new(), UnrolledStack::<String>::default());
+    }
+
+    #[test]
+    fn debug_test() {
+        let stack: UnrolledStack<i32, 2> = (1..4).collect();
+        assert_eq!(format!("{:?}", stack), "[3, 2, 1]");
+        assert_eq!(format!("{:?}", UnrolledStack::<i32>::new()), "[]");
+    }
+
+    #[test]
+    fn boundary_test() {
+        // Push and pop back and forth across node boundaries.
+        let mut stack: UnrolledStack<i32, 3> = UnrolledStack::new();
+        for round in 1..10 {
+            for i in 0..round {
+                stack.push(i);
+            }
+            assert_eq!(stack.len(), round as usize);
+            assert_eq!(stack.peek(), Some(&(round - 1)));
+            for i in (0..round).rev() {
+                assert_eq!(stack.pop(), Some(i));
+            }
+            assert!(stack.is_empty());
+            assert!(stack.head.is_none());
+        }
+    }
+}